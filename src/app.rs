@@ -1,8 +1,9 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::kline::KLine;
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct App {
     kline: KLine,
 }
@@ -20,7 +21,7 @@ impl App {
         // 加载中文字体
         custom_font(&cc.egui_ctx);
         if let Some(storage) = cc.storage {
-            eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
+            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
         }
         Default::default()
     }
@@ -0,0 +1,456 @@
+use egui::{ComboBox, DragValue, Ui};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    i18n::t,
+    indicator::atr,
+    real_data::{Candle, RealData},
+    utils::DateTimeUtils,
+};
+
+/// k线的构建方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum BarMode {
+    /// 按时间周期，每根k线对应一个candle
+    Time,
+    /// 砖形图，价格每变动一个砖块大小生成一块砖
+    Renko,
+    /// 等幅k线，每根k线的最高价和最低价相差固定幅度
+    Range,
+    /// 点数图，上涨列画X，下跌列画O
+    PointFigure,
+}
+
+impl BarMode {
+    pub const ALL: [BarMode; 4] = [
+        BarMode::Time,
+        BarMode::Renko,
+        BarMode::Range,
+        BarMode::PointFigure,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BarMode::Time => t("时间K线"),
+            BarMode::Renko => t("砖形图"),
+            BarMode::Range => t("等幅K线"),
+            BarMode::PointFigure => t("点数图"),
+        }
+    }
+}
+
+/// 砖形图砖块大小的计算方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum BrickSizeMode {
+    /// 固定大小
+    Fixed,
+    /// 使用构建时最后一根k线的ATR
+    Atr,
+}
+
+impl BrickSizeMode {
+    pub const ALL: [BrickSizeMode; 2] = [BrickSizeMode::Fixed, BrickSizeMode::Atr];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BrickSizeMode::Fixed => t("固定大小"),
+            BrickSizeMode::Atr => "ATR",
+        }
+    }
+}
+
+/// 点数图判断价格变动所用的价格
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PointFigureMethod {
+    /// 只使用收盘价
+    Close,
+    /// 上涨列使用最高价，下跌列使用最低价
+    HighLow,
+}
+
+impl PointFigureMethod {
+    pub const ALL: [PointFigureMethod; 2] = [PointFigureMethod::Close, PointFigureMethod::HighLow];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PointFigureMethod::Close => t("收盘价"),
+            PointFigureMethod::HighLow => t("最高最低价"),
+        }
+    }
+}
+
+/// 非时间k线的设置，会随App一起存储
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BrickSettings {
+    pub mode: BarMode,
+    pub size_mode: BrickSizeMode,
+    /// 砖块大小、等幅k线的幅度或点数图的格值
+    pub size: f64,
+    /// ATR的周期
+    pub atr_period: usize,
+    /// 点数图反转需要的格数
+    pub reversal: usize,
+    pub point_figure_method: PointFigureMethod,
+}
+
+impl Default for BrickSettings {
+    fn default() -> Self {
+        Self {
+            mode: BarMode::Time,
+            size_mode: BrickSizeMode::Fixed,
+            size: 1.0,
+            atr_period: 14,
+            reversal: 3,
+            point_figure_method: PointFigureMethod::Close,
+        }
+    }
+}
+
+impl BrickSettings {
+    /// 根据设置计算砖块大小，ATR不足周期时使用固定大小
+    fn brick_size(&self, candles: &[Candle]) -> f64 {
+        match (self.mode, self.size_mode) {
+            (BarMode::Renko, BrickSizeMode::Atr) => atr(candles, self.atr_period)
+                .last()
+                .copied()
+                .flatten()
+                .unwrap_or(self.size),
+            _ => self.size,
+        }
+    }
+
+    /// 工具栏中的设置项
+    pub fn show_toolbar(&mut self, ui: &mut Ui) {
+        ComboBox::from_id_source("bar_mode")
+            .selected_text(self.mode.name())
+            .show_ui(ui, |ui| {
+                BarMode::ALL.iter().for_each(|mode| {
+                    ui.selectable_value(&mut self.mode, *mode, mode.name());
+                });
+            });
+        match self.mode {
+            BarMode::Time => {}
+            BarMode::Renko => {
+                ComboBox::from_id_source("brick_size_mode")
+                    .selected_text(self.size_mode.name())
+                    .show_ui(ui, |ui| {
+                        BrickSizeMode::ALL.iter().for_each(|size_mode| {
+                            ui.selectable_value(&mut self.size_mode, *size_mode, size_mode.name());
+                        });
+                    });
+                match self.size_mode {
+                    BrickSizeMode::Fixed => {
+                        ui.label(t("砖块"));
+                        ui.add(
+                            DragValue::new(&mut self.size)
+                                .clamp_range(0.01..=f64::MAX)
+                                .speed(0.1),
+                        );
+                    }
+                    BrickSizeMode::Atr => {
+                        ui.label(t("周期"));
+                        ui.add(DragValue::new(&mut self.atr_period).clamp_range(1..=500));
+                    }
+                }
+            }
+            BarMode::Range => {
+                ui.label(t("幅度"));
+                ui.add(
+                    DragValue::new(&mut self.size)
+                        .clamp_range(0.01..=f64::MAX)
+                        .speed(0.1),
+                );
+            }
+            BarMode::PointFigure => {
+                ui.label(t("格值"));
+                ui.add(
+                    DragValue::new(&mut self.size)
+                        .clamp_range(0.01..=f64::MAX)
+                        .speed(0.1),
+                );
+                ui.label(t("反转"));
+                ui.add(DragValue::new(&mut self.reversal).clamp_range(1..=10));
+                ComboBox::from_id_source("point_figure_method")
+                    .selected_text(self.point_figure_method.name())
+                    .show_ui(ui, |ui| {
+                        PointFigureMethod::ALL.iter().for_each(|method| {
+                            ui.selectable_value(
+                                &mut self.point_figure_method,
+                                *method,
+                                method.name(),
+                            );
+                        });
+                    });
+            }
+        }
+    }
+}
+
+/// 正在形成的砖块或等幅k线
+#[derive(Debug, Clone)]
+struct Pending {
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    /// 覆盖的第一根k线的时间
+    start: String,
+    /// 覆盖的最后一根k线的时间
+    end: String,
+    volume: f64,
+    open_interest: Option<f64>,
+}
+
+impl Pending {
+    fn new(candle: &Candle, price: f64) -> Self {
+        Self {
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            start: candle.datetime.to_owned(),
+            end: candle.datetime.to_owned(),
+            volume: 0.0,
+            open_interest: candle.open_interest,
+        }
+    }
+}
+
+/// 根据k线序列增量构建砖形图和等幅k线
+///
+/// 新数据到来时只处理新增的k线，设置改变或数据被替换时重新构建
+#[derive(Debug, Default)]
+pub struct BrickBuilder {
+    /// 构建当前数据所用的设置
+    settings: Option<BrickSettings>,
+    /// 第一根k线的时间，用于判断数据是否被替换
+    first_datetime: Option<String>,
+    /// 已经处理的k线个数
+    processed: usize,
+    brick_size: f64,
+    /// 已经完成的砖块
+    bricks: Vec<RealData>,
+    pending: Option<Pending>,
+    /// 砖形图最后一块砖的下沿和上沿
+    renko_range: Option<(f64, f64)>,
+    /// 点数图当前列的方向，true为上涨列，还没有确定方向时为None
+    column_up: Option<bool>,
+}
+
+impl BrickBuilder {
+    /// 点数图的格值
+    pub fn box_size(&self) -> f64 {
+        self.brick_size
+    }
+
+    /// 返回构建好的数据，等幅k线和点数图包括正在形成的最后一根
+    pub fn update(&mut self, settings: &BrickSettings, real_datas: &[RealData]) -> Vec<RealData> {
+        let first_datetime = real_datas
+            .first()
+            .map(|real_data| real_data.candle.datetime.to_owned());
+        if self.settings.as_ref() != Some(settings)
+            || self.first_datetime != first_datetime
+            || self.processed > real_datas.len()
+        {
+            let candles = real_datas
+                .iter()
+                .map(|real_data| real_data.candle.to_owned())
+                .collect::<Vec<Candle>>();
+            *self = Self {
+                settings: Some(settings.to_owned()),
+                first_datetime,
+                brick_size: settings.brick_size(&candles),
+                ..Default::default()
+            };
+        }
+        if !(self.brick_size > 0.0 && self.brick_size.is_finite()) {
+            return vec![];
+        }
+        real_datas[self.processed..]
+            .iter()
+            .for_each(|real_data| match settings.mode {
+                BarMode::Renko => self.push_renko(&real_data.candle),
+                BarMode::PointFigure => self.push_point_figure(&real_data.candle),
+                _ => self.push_range(&real_data.candle),
+            });
+        self.processed = real_datas.len();
+
+        let mut bricks = self.bricks.to_owned();
+        let show_pending = match settings.mode {
+            BarMode::Range => true,
+            BarMode::PointFigure => self.column_up.is_some(),
+            _ => false,
+        };
+        if show_pending {
+            if let Some(pending) = &self.pending {
+                bricks.push(Self::to_real_data(pending, bricks.len()));
+            }
+        }
+        bricks
+    }
+
+    /// 收盘价每超过上一块砖一个砖块大小生成一块砖，反转需要超过两个砖块大小
+    fn push_renko(&mut self, candle: &Candle) {
+        let size = self.brick_size;
+        self.add_volume(candle);
+        let (mut low, mut high) = *self.renko_range.get_or_insert((candle.close, candle.close));
+        let mut bricks = vec![];
+        while candle.close >= high + size {
+            bricks.push((high, high + size));
+            low = high;
+            high += size;
+        }
+        if bricks.is_empty() {
+            while candle.close <= low - size {
+                bricks.push((low, low - size));
+                high = low;
+                low -= size;
+            }
+        }
+        self.renko_range = Some((low, high));
+        bricks.into_iter().for_each(|(open, close)| {
+            let pending = self
+                .pending
+                .get_or_insert_with(|| Pending::new(candle, open));
+            pending.open = open;
+            pending.close = close;
+            pending.high = open.max(close);
+            pending.low = open.min(close);
+            self.finish();
+        });
+    }
+
+    /// 按开盘、最低、最高、收盘(阴线为开盘、最高、最低、收盘)的顺序模拟价格走势
+    fn push_range(&mut self, candle: &Candle) {
+        let size = self.brick_size;
+        if self.pending.is_none() {
+            self.pending = Some(Pending::new(candle, candle.open));
+        }
+        self.add_volume(candle);
+        let prices = if candle.close >= candle.open {
+            [candle.open, candle.low, candle.high, candle.close]
+        } else {
+            [candle.open, candle.high, candle.low, candle.close]
+        };
+        prices.iter().for_each(|price| loop {
+            let pending = self
+                .pending
+                .get_or_insert_with(|| Pending::new(candle, *price));
+            let close = if *price > pending.low + size {
+                pending.low + size
+            } else if *price < pending.high - size {
+                pending.high - size
+            } else {
+                pending.high = pending.high.max(*price);
+                pending.low = pending.low.min(*price);
+                pending.close = *price;
+                break;
+            };
+            pending.high = pending.high.max(close);
+            pending.low = pending.low.min(close);
+            pending.close = close;
+            self.finish();
+            // 下一根k线从上一根的收盘价开始
+            self.pending = Some(Pending::new(candle, close));
+        });
+    }
+
+    /// 成交量计入正在形成的砖块，没有时新建一个
+    fn add_volume(&mut self, candle: &Candle) {
+        let pending = self
+            .pending
+            .get_or_insert_with(|| Pending::new(candle, candle.close));
+        pending.volume += candle.volume;
+        pending.end = candle.datetime.to_owned();
+        pending.open_interest = candle.open_interest;
+    }
+
+    /// 点数图每一列的价格对齐到格值的整数倍，价格反向变动reversal格时开始新的一列
+    ///
+    /// 列的开盘价和收盘价为列的两端，上涨列开盘价在下
+    fn push_point_figure(&mut self, candle: &Candle) {
+        let size = self.brick_size;
+        let reversal = self
+            .settings
+            .as_ref()
+            .map_or(3, |settings| settings.reversal) as f64;
+        let (high_price, low_price) = match self.settings.as_ref().map(|s| s.point_figure_method) {
+            Some(PointFigureMethod::HighLow) => (candle.high, candle.low),
+            _ => (candle.close, candle.close),
+        };
+        let floor = |price: f64| (price / size).floor() * size;
+        let ceil = |price: f64| (price / size).ceil() * size;
+        let pending = self.pending.get_or_insert_with(|| {
+            let level = floor(candle.close);
+            Pending::new(candle, level)
+        });
+        // 反转时新列的(下沿, 上沿, 方向)
+        let mut next_column = None;
+        match self.column_up {
+            None => {
+                if high_price >= pending.high + size {
+                    pending.high = floor(high_price);
+                    self.column_up = Some(true);
+                } else if low_price <= pending.low - size {
+                    pending.low = ceil(low_price);
+                    self.column_up = Some(false);
+                }
+            }
+            Some(true) => {
+                if high_price >= pending.high + size {
+                    pending.high = floor(high_price);
+                } else if low_price <= pending.high - reversal * size {
+                    next_column = Some((ceil(low_price), pending.high - size, false));
+                }
+            }
+            Some(false) => {
+                if low_price <= pending.low - size {
+                    pending.low = ceil(low_price);
+                } else if high_price >= pending.low + reversal * size {
+                    next_column = Some((pending.low + size, floor(high_price), true));
+                }
+            }
+        }
+        if let Some((low, high, up)) = next_column {
+            self.finish();
+            let mut pending = Pending::new(candle, low);
+            pending.high = high;
+            self.pending = Some(pending);
+            self.column_up = Some(up);
+        }
+        if let (Some(pending), Some(up)) = (self.pending.as_mut(), self.column_up) {
+            (pending.open, pending.close) = if up {
+                (pending.low, pending.high)
+            } else {
+                (pending.high, pending.low)
+            };
+        }
+        self.add_volume(candle);
+    }
+
+    /// 完成正在形成的砖块
+    fn finish(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            self.bricks
+                .push(Self::to_real_data(&pending, self.bricks.len()));
+        }
+    }
+
+    fn to_real_data(pending: &Pending, index: usize) -> RealData {
+        let candle = Candle {
+            open: pending.open,
+            close: pending.close,
+            high: pending.high,
+            low: pending.low,
+            volume: pending.volume,
+            datetime: pending.end.to_owned(),
+            open_interest: pending.open_interest,
+            turnover: None,
+        };
+        RealData {
+            start_datetime: DateTimeUtils::format_datetime_string(&pending.start),
+            ..RealData::new(&candle, index as f64 + 1.0)
+        }
+    }
+}
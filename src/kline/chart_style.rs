@@ -0,0 +1,139 @@
+use egui::{
+    plot::{BoxPlot, Line, PlotUi},
+    Color32, Stroke,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    i18n::t,
+    real_data::{Direction, RealData},
+};
+
+/// 收盘价折线和面积图的颜色
+const LINE_COLOR: Color32 = Color32::from_rgb(30, 144, 255);
+
+/// 面积图渐变填充的层数，越靠近折线叠加的层数越多，颜色越深
+const AREA_LAYERS: usize = 6;
+
+/// 美国线左右刻度的长度
+const TICK_WIDTH: f64 = 0.3;
+
+/// 蜡烛图的绘制方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ChartStyle {
+    /// 实心蜡烛图
+    Candle,
+    /// 空心蜡烛图，收盘价高于开盘价时为空心
+    HollowCandle,
+    /// 美国线，左刻度为开盘价，右刻度为收盘价
+    OhlcBar,
+    /// 收盘价折线
+    Line,
+    /// 收盘价面积图
+    Area,
+}
+
+impl ChartStyle {
+    pub const ALL: [ChartStyle; 5] = [
+        ChartStyle::Candle,
+        ChartStyle::HollowCandle,
+        ChartStyle::OhlcBar,
+        ChartStyle::Line,
+        ChartStyle::Area,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChartStyle::Candle => t("蜡烛图"),
+            ChartStyle::HollowCandle => t("空心蜡烛图"),
+            ChartStyle::OhlcBar => t("美国线"),
+            ChartStyle::Line => t("折线图"),
+            ChartStyle::Area => t("面积图"),
+        }
+    }
+
+    /// 在plot_ui中绘制real_datas，需要在设置plot_bounds之后调用
+    pub fn draw(&self, plot_ui: &mut PlotUi, real_datas: &[RealData]) {
+        match self {
+            ChartStyle::Candle => {
+                plot_ui.box_plot(BoxPlot::new(
+                    real_datas
+                        .iter()
+                        .map(|real_data| real_data.box_elem.to_owned())
+                        .collect(),
+                ));
+            }
+            ChartStyle::HollowCandle => {
+                plot_ui.box_plot(BoxPlot::new(
+                    real_datas
+                        .iter()
+                        .map(|real_data| {
+                            let mut box_elem = real_data.box_elem.to_owned();
+                            if real_data.direction == Direction::Up {
+                                box_elem.fill = Color32::TRANSPARENT;
+                            }
+                            box_elem
+                        })
+                        .collect(),
+                ));
+            }
+            ChartStyle::OhlcBar => {
+                let bounds = plot_ui.plot_bounds();
+                real_datas
+                    .iter()
+                    .filter(|real_data| {
+                        real_data.box_elem.argument >= bounds.min()[0] - 1.0
+                            && real_data.box_elem.argument <= bounds.max()[0] + 1.0
+                    })
+                    .for_each(|real_data| {
+                        let x = real_data.box_elem.argument;
+                        let spread = &real_data.box_elem.spread;
+                        let (open, close) = real_data.open_close();
+                        // 竖线会重复经过一次，这样每根k线只需要一条折线
+                        plot_ui.line(
+                            Line::new(vec![
+                                [x - TICK_WIDTH, open],
+                                [x, open],
+                                [x, spread.upper_whisker],
+                                [x, spread.lower_whisker],
+                                [x, close],
+                                [x + TICK_WIDTH, close],
+                            ])
+                            .stroke(real_data.box_elem.stroke),
+                        );
+                    });
+            }
+            ChartStyle::Line => {
+                plot_ui.line(Line::new(Self::closes(real_datas)).color(LINE_COLOR));
+            }
+            ChartStyle::Area => {
+                let closes = Self::closes(real_datas);
+                let bounds = plot_ui.plot_bounds();
+                let (bottom, top) = (bounds.min()[1], bounds.max()[1]);
+                // egui的填充只有单一颜色，用多层填充叠加出从折线向下变淡的效果
+                (0..AREA_LAYERS).for_each(|layer| {
+                    let reference = bottom + (top - bottom) * layer as f64 / AREA_LAYERS as f64;
+                    plot_ui.line(
+                        Line::new(
+                            closes
+                                .iter()
+                                .map(|[x, y]| [*x, y.max(reference)])
+                                .collect::<Vec<[f64; 2]>>(),
+                        )
+                        .stroke(Stroke::new(0.0, LINE_COLOR))
+                        .fill(reference as f32),
+                    );
+                });
+                plot_ui.line(Line::new(closes).color(LINE_COLOR).width(1.5));
+            }
+        }
+    }
+
+    /// 每根k线收盘价的坐标
+    fn closes(real_datas: &[RealData]) -> Vec<[f64; 2]> {
+        real_datas
+            .iter()
+            .map(|real_data| [real_data.box_elem.argument, real_data.open_close().1])
+            .collect()
+    }
+}
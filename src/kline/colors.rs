@@ -0,0 +1,176 @@
+use egui::{Color32, Grid, Ui};
+use serde::{Deserialize, Serialize};
+
+use super::{i18n::t, real_data::Direction};
+
+/// 涨跌配色方案
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ColorConvention {
+    /// 红涨绿跌，国内习惯
+    RedUp,
+    /// 绿涨红跌，海外习惯
+    GreenUp,
+    /// 自定义颜色
+    Custom,
+}
+
+impl ColorConvention {
+    pub const ALL: [ColorConvention; 3] = [
+        ColorConvention::RedUp,
+        ColorConvention::GreenUp,
+        ColorConvention::Custom,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorConvention::RedUp => t("红涨绿跌"),
+            ColorConvention::GreenUp => t("绿涨红跌"),
+            ColorConvention::Custom => t("自定义"),
+        }
+    }
+}
+
+/// 上涨、下跌和十字星各自的颜色
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct DirectionColors {
+    pub up: Color32,
+    pub down: Color32,
+    pub doji: Color32,
+}
+
+impl DirectionColors {
+    pub fn get(&self, direction: Direction) -> Color32 {
+        match direction {
+            Direction::Up => self.up,
+            Direction::Down => self.down,
+            Direction::Doji => self.doji,
+        }
+    }
+
+    /// 上涨和下跌的颜色互换
+    fn swapped(&self) -> Self {
+        Self {
+            up: self.down,
+            down: self.up,
+            doji: self.doji,
+        }
+    }
+}
+
+/// 蜡烛图和成交量图的配色，属于图表主题的一部分
+///
+/// egui的BoxElem只有一个描边颜色，所以影线颜色同时也是实体的边框颜色。
+/// 红涨绿跌和绿涨红跌方案下十字星使用自定义的十字星颜色，以适应深色和浅色背景
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CandleColors {
+    pub convention: ColorConvention,
+    /// 自定义的实体颜色
+    pub body: DirectionColors,
+    /// 自定义的影线颜色
+    pub wick: DirectionColors,
+    /// 自定义的成交量颜色
+    pub volume: DirectionColors,
+}
+
+impl Default for CandleColors {
+    fn default() -> Self {
+        Self::with_doji(Color32::BLACK)
+    }
+}
+
+impl CandleColors {
+    /// 红涨绿跌，十字星为doji颜色
+    pub fn with_doji(doji: Color32) -> Self {
+        let (body, wick, volume) = Self::red_up(doji);
+        Self {
+            convention: ColorConvention::RedUp,
+            body,
+            wick,
+            volume,
+        }
+    }
+
+    /// 红涨绿跌的(实体, 影线, 成交量)颜色
+    fn red_up(doji: Color32) -> (DirectionColors, DirectionColors, DirectionColors) {
+        let body = DirectionColors {
+            up: Color32::RED,
+            down: Color32::GREEN,
+            doji,
+        };
+        // 十字星的成交量按上涨显示
+        let volume = DirectionColors {
+            up: Color32::RED,
+            down: Color32::GREEN,
+            doji: Color32::RED,
+        };
+        (body, body, volume)
+    }
+
+    /// 当前配色方案下的(实体, 影线, 成交量)颜色
+    fn palette(&self) -> (DirectionColors, DirectionColors, DirectionColors) {
+        match self.convention {
+            ColorConvention::RedUp => Self::red_up(self.body.doji),
+            ColorConvention::GreenUp => {
+                let (body, wick, volume) = Self::red_up(self.body.doji);
+                let mut volume = volume.swapped();
+                volume.doji = volume.up;
+                (body.swapped(), wick.swapped(), volume)
+            }
+            ColorConvention::Custom => (self.body, self.wick, self.volume),
+        }
+    }
+
+    pub fn body(&self, direction: Direction) -> Color32 {
+        self.palette().0.get(direction)
+    }
+
+    pub fn wick(&self, direction: Direction) -> Color32 {
+        self.palette().1.get(direction)
+    }
+
+    pub fn volume(&self, direction: Direction) -> Color32 {
+        self.palette().2.get(direction)
+    }
+
+    /// 配色设置项
+    pub fn show_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ColorConvention::ALL.iter().for_each(|convention| {
+                ui.selectable_value(&mut self.convention, *convention, convention.name());
+            });
+        });
+        if self.convention != ColorConvention::Custom {
+            ui.horizontal(|ui| {
+                ui.label(t("十字星"));
+                if ui.color_edit_button_srgba(&mut self.body.doji).changed() {
+                    self.wick.doji = self.body.doji;
+                }
+            });
+            return;
+        }
+        Grid::new("candle_colors").show(ui, |ui| {
+            ui.label("");
+            ui.label(t("上涨"));
+            ui.label(t("下跌"));
+            ui.label(t("十字星"));
+            ui.end_row();
+            [
+                (t("实体"), &mut self.body),
+                (t("影线"), &mut self.wick),
+                (t("成交量"), &mut self.volume),
+            ]
+            .into_iter()
+            .for_each(|(name, colors)| {
+                ui.label(name);
+                ui.color_edit_button_srgba(&mut colors.up);
+                ui.color_edit_button_srgba(&mut colors.down);
+                ui.color_edit_button_srgba(&mut colors.doji);
+                ui.end_row();
+            });
+        });
+        if ui.button(t("重置")).clicked() {
+            (self.body, self.wick, self.volume) = Self::red_up(self.body.doji);
+        }
+    }
+}
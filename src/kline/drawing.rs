@@ -0,0 +1,1161 @@
+use std::{collections::BTreeMap, f64::consts::TAU};
+
+use egui::{
+    emath::Rot2,
+    plot::{Line, LineStyle, PlotBounds, PlotPoint, PlotUi, Points, Polygon, Text},
+    Align2, Button, Color32, ComboBox, Context, DragValue, FontId, Grid, Id, Key, PointerButton,
+    Pos2, Rect, RichText, Stroke, TextEdit, Ui, Vec2, Window,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    fibonacci::FibonacciSettings,
+    history::{ChartCommand, ListEdit},
+    i18n::{t, tf},
+    instrument::Instrument,
+    real_data::RealData,
+};
+
+/// 指针距离画线多少像素以内算作选中
+const HIT_DISTANCE: f32 = 6.0;
+/// 椭圆用多少段折线绘制
+const ELLIPSE_SEGMENTS: usize = 64;
+/// 文字背景超出文字的像素
+const TEXT_PADDING: f32 = 4.0;
+
+/// 画线的种类
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DrawingKind {
+    /// 两点之间的线段
+    TrendLine,
+    /// 从第一个点出发，经过第二个点向外延伸
+    Ray,
+    /// 经过两个点，向两端延伸
+    ExtendedLine,
+    HorizontalLine,
+    VerticalLine,
+    /// 两点之间的斐波那契回撤
+    FibRetracement,
+    /// 按前两点的幅度，从第三个点开始的斐波那契扩展
+    FibExtension,
+    /// 以两点之间的k线个数为单位的斐波那契时间周期
+    FibTimeZones,
+    /// 以两点为对角的矩形
+    Rectangle,
+    /// 以两点为外接矩形对角的椭圆
+    Ellipse,
+    /// 经过前两点的直线和经过第三个点的平行线组成的通道
+    ParallelChannel,
+    /// 从第一个点指向第二个点的箭头
+    Arrow,
+    /// 文字注释，锚点在左下角
+    Text,
+}
+
+impl DrawingKind {
+    pub const ALL: [DrawingKind; 13] = [
+        DrawingKind::TrendLine,
+        DrawingKind::Ray,
+        DrawingKind::ExtendedLine,
+        DrawingKind::HorizontalLine,
+        DrawingKind::VerticalLine,
+        DrawingKind::FibRetracement,
+        DrawingKind::FibExtension,
+        DrawingKind::FibTimeZones,
+        DrawingKind::Rectangle,
+        DrawingKind::Ellipse,
+        DrawingKind::ParallelChannel,
+        DrawingKind::Arrow,
+        DrawingKind::Text,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DrawingKind::TrendLine => t("趋势线"),
+            DrawingKind::Ray => t("射线"),
+            DrawingKind::ExtendedLine => t("延长线"),
+            DrawingKind::HorizontalLine => t("水平线"),
+            DrawingKind::VerticalLine => t("垂直线"),
+            DrawingKind::FibRetracement => t("斐波那契回撤"),
+            DrawingKind::FibExtension => t("斐波那契扩展"),
+            DrawingKind::FibTimeZones => t("斐波那契时间周期"),
+            DrawingKind::Rectangle => t("矩形"),
+            DrawingKind::Ellipse => t("椭圆"),
+            DrawingKind::ParallelChannel => t("平行通道"),
+            DrawingKind::Arrow => t("箭头"),
+            DrawingKind::Text => t("文字"),
+        }
+    }
+
+    /// 锚点的个数
+    fn anchor_count(&self) -> usize {
+        match self {
+            DrawingKind::HorizontalLine | DrawingKind::VerticalLine | DrawingKind::Text => 1,
+            DrawingKind::FibExtension | DrawingKind::ParallelChannel => 3,
+            _ => 2,
+        }
+    }
+}
+
+/// 画线的线型
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DrawingLineStyle {
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl DrawingLineStyle {
+    pub const ALL: [DrawingLineStyle; 3] = [
+        DrawingLineStyle::Solid,
+        DrawingLineStyle::Dashed,
+        DrawingLineStyle::Dotted,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DrawingLineStyle::Solid => t("实线"),
+            DrawingLineStyle::Dashed => t("虚线"),
+            DrawingLineStyle::Dotted => t("点线"),
+        }
+    }
+
+    fn line_style(&self) -> LineStyle {
+        match self {
+            DrawingLineStyle::Solid => LineStyle::Solid,
+            DrawingLineStyle::Dashed => LineStyle::dashed_loose(),
+            DrawingLineStyle::Dotted => LineStyle::dotted_loose(),
+        }
+    }
+}
+
+/// 画线的样式，在画线属性窗口中修改
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DrawingStyle {
+    pub color: Color32,
+    pub width: f32,
+    pub line_style: DrawingLineStyle,
+    /// 矩形、椭圆和通道的填充色，也是文字的背景色，透明时不填充
+    pub fill: Color32,
+    /// 文字的大小
+    pub font_size: f32,
+}
+
+impl Default for DrawingStyle {
+    fn default() -> Self {
+        Self {
+            color: Color32::from_rgb(41, 98, 255),
+            width: 1.5,
+            line_style: DrawingLineStyle::Solid,
+            fill: Color32::from_rgba_unmultiplied(41, 98, 255, 40),
+            font_size: 14.0,
+        }
+    }
+}
+
+impl DrawingStyle {
+    fn show_ui(&mut self, ui: &mut Ui) {
+        Grid::new("drawing_style").show(ui, |ui| {
+            ui.label(t("颜色"));
+            ui.color_edit_button_srgba(&mut self.color);
+            ui.end_row();
+            ui.label(t("线宽"));
+            ui.add(
+                DragValue::new(&mut self.width)
+                    .clamp_range(0.5..=5.0)
+                    .speed(0.1),
+            );
+            ui.end_row();
+            ui.label(t("线型"));
+            ComboBox::from_id_source("drawing_line_style")
+                .selected_text(self.line_style.name())
+                .show_ui(ui, |ui| {
+                    DrawingLineStyle::ALL.iter().for_each(|style| {
+                        ui.selectable_value(&mut self.line_style, *style, style.name());
+                    });
+                });
+            ui.end_row();
+            ui.label(t("填充"));
+            ui.color_edit_button_srgba(&mut self.fill);
+            ui.end_row();
+            ui.label(t("字号"));
+            ui.add(DragValue::new(&mut self.font_size).clamp_range(8.0..=48.0));
+            ui.end_row();
+        });
+    }
+}
+
+/// 画线的锚点，用k线的日期和价格定位，拖动、缩放和数据更新后位置不变
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Anchor {
+    /// 锚定的k线日期
+    pub datetime: String,
+    /// 相对这根k线的x轴偏移，锚点在两根k线之间或最后一根k线之后时不为0
+    pub offset: f64,
+    pub price: f64,
+}
+
+impl Anchor {
+    /// 由图中的坐标创建锚点，没有k线时返回None
+    pub fn from_plot(real_datas: &[RealData], x: f64, price: f64) -> Option<Self> {
+        let last = real_datas.len().checked_sub(1)?;
+        let index = real_datas
+            .partition_point(|real_data| real_data.box_elem.argument < x)
+            .min(last);
+        // 取左右两根中较近的一根
+        let index = match index.checked_sub(1) {
+            Some(prev)
+                if x - real_datas[prev].box_elem.argument
+                    < real_datas[index].box_elem.argument - x =>
+            {
+                prev
+            }
+            _ => index,
+        };
+        let datetime = &real_datas[index].candle.datetime;
+        // 砖形图中多根k线的日期可能相同，偏移相对日期相同的第一根计算
+        let first = real_datas.partition_point(|real_data| real_data.candle.datetime < *datetime);
+        Some(Self {
+            datetime: datetime.to_owned(),
+            offset: x - real_datas[first].box_elem.argument,
+            price,
+        })
+    }
+
+    /// 锚点在图中的坐标，日期不存在时(如切换周期后)使用包含这个时间的k线
+    pub fn to_plot(&self, real_datas: &[RealData]) -> Option<[f64; 2]> {
+        let last = real_datas.len().checked_sub(1)?;
+        let first =
+            real_datas.partition_point(|real_data| real_data.candle.datetime < self.datetime);
+        let index = match real_datas.get(first) {
+            Some(real_data) if real_data.candle.datetime == self.datetime => first,
+            _ => first.saturating_sub(1).min(last),
+        };
+        Some([
+            real_datas[index].box_elem.argument + self.offset,
+            self.price,
+        ])
+    }
+}
+
+/// 画线中的一条折线
+struct Polyline {
+    points: Vec<[f64; 2]>,
+    color: Color32,
+    style: LineStyle,
+}
+
+/// 一条画线
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Drawing {
+    pub kind: DrawingKind,
+    pub anchors: Vec<Anchor>,
+    pub style: DrawingStyle,
+    /// 文字注释的内容
+    pub text: String,
+}
+
+impl Default for Drawing {
+    fn default() -> Self {
+        Self {
+            kind: DrawingKind::TrendLine,
+            anchors: vec![],
+            style: Default::default(),
+            text: String::new(),
+        }
+    }
+}
+
+impl Drawing {
+    /// 锚点在图中的坐标
+    fn points(&self, real_datas: &[RealData]) -> Vec<[f64; 2]> {
+        self.anchors
+            .iter()
+            .filter_map(|anchor| anchor.to_plot(real_datas))
+            .collect()
+    }
+
+    /// 使用画线样式的折线
+    fn polyline(&self, points: Vec<[f64; 2]>) -> Polyline {
+        Polyline {
+            points,
+            color: self.style.color,
+            style: self.style.line_style.line_style(),
+        }
+    }
+
+    /// 需要绘制的折线，射线、延长线、水平线和垂直线延伸到可见范围之外
+    fn polylines(
+        &self,
+        points: &[[f64; 2]],
+        plot_ui: &PlotUi,
+        fibonacci: &FibonacciSettings,
+    ) -> Vec<Polyline> {
+        let bounds = plot_ui.plot_bounds();
+        let (min, max) = (bounds.min(), bounds.max());
+        // 斐波那契画线中连接锚点的线用虚线，每条线使用自己的颜色
+        let guide = |points: Vec<[f64; 2]>| Polyline {
+            points,
+            color: self.style.color,
+            style: LineStyle::dashed_loose(),
+        };
+        let level = |points: Vec<[f64; 2]>, color: Color32| Polyline {
+            points,
+            color,
+            style: self.style.line_style.line_style(),
+        };
+        match (self.kind, points) {
+            (DrawingKind::TrendLine, [a, b]) => vec![self.polyline(vec![*a, *b])],
+            (DrawingKind::Ray, [a, b]) => vec![self.polyline(vec![*a, extend(*a, *b, &bounds)])],
+            (DrawingKind::ExtendedLine, [a, b]) => {
+                vec![self.polyline(vec![extend(*b, *a, &bounds), extend(*a, *b, &bounds)])]
+            }
+            (DrawingKind::HorizontalLine, [a]) => {
+                vec![self.polyline(vec![[min[0], a[1]], [max[0], a[1]]])]
+            }
+            (DrawingKind::VerticalLine, [a]) => {
+                vec![self.polyline(vec![[a[0], min[1]], [a[0], max[1]]])]
+            }
+            (DrawingKind::FibRetracement, [a, b]) => {
+                let (left, right) = (a[0].min(b[0]), a[0].max(b[0]));
+                let levels = fibonacci.retracement_levels(a[1], b[1]).into_iter();
+                std::iter::once(guide(vec![*a, *b]))
+                    .chain(
+                        levels.map(|(_, price, color)| {
+                            level(vec![[left, price], [right, price]], color)
+                        }),
+                    )
+                    .collect()
+            }
+            (DrawingKind::FibExtension, [a, b, c]) => {
+                let (left, right) = (c[0], c[0].max(b[0]) + (b[0] - a[0]).abs());
+                let levels = fibonacci.extension_levels(a[1], b[1], c[1]).into_iter();
+                std::iter::once(guide(vec![*a, *b, *c]))
+                    .chain(
+                        levels.map(|(_, price, color)| {
+                            level(vec![[left, price], [right, price]], color)
+                        }),
+                    )
+                    .collect()
+            }
+            (DrawingKind::FibTimeZones, [a, b]) => fibonacci
+                .time_zone_levels(a[0], b[0])
+                .into_iter()
+                .map(|(_, x, color)| level(vec![[x, min[1]], [x, max[1]]], color))
+                .collect(),
+            (DrawingKind::Rectangle, [a, b]) => {
+                vec![self.polyline(vec![*a, [b[0], a[1]], *b, [a[0], b[1]], *a])]
+            }
+            (DrawingKind::Ellipse, [a, b]) => vec![self.polyline(ellipse(*a, *b))],
+            (DrawingKind::ParallelChannel, [a, b, c]) => {
+                let offset = channel_offset(*a, *b, *c);
+                let shift = |point: &[f64; 2], ratio: f64| [point[0], point[1] + offset * ratio];
+                vec![
+                    self.polyline(vec![*a, *b]),
+                    self.polyline(vec![shift(a, 1.0), shift(b, 1.0)]),
+                    guide(vec![shift(a, 0.5), shift(b, 0.5)]),
+                ]
+            }
+            (DrawingKind::Arrow, [a, b]) => {
+                // 箭头在屏幕上计算，保证缩放后形状不变
+                let (from, to) = (
+                    plot_ui.screen_from_plot(PlotPoint::from(*a)),
+                    plot_ui.screen_from_plot(PlotPoint::from(*b)),
+                );
+                let direction = (to - from).normalized();
+                let size = 8.0 + self.style.width * 2.0;
+                let wing = |angle: f32| {
+                    let point =
+                        plot_ui.plot_from_screen(to - Rot2::from_angle(angle) * direction * size);
+                    [point.x, point.y]
+                };
+                vec![
+                    self.polyline(vec![*a, *b]),
+                    Polyline {
+                        points: vec![wing(0.5), *b, wing(-0.5)],
+                        color: self.style.color,
+                        style: LineStyle::Solid,
+                    },
+                ]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// 需要填充的区域，文字注释为文字的背景
+    fn fill(&self, points: &[[f64; 2]], plot_ui: &PlotUi) -> Option<Vec<[f64; 2]>> {
+        match (self.kind, points) {
+            (DrawingKind::Rectangle, [a, b]) => Some(vec![*a, [b[0], a[1]], *b, [a[0], b[1]]]),
+            (DrawingKind::Ellipse, [a, b]) => Some(ellipse(*a, *b)),
+            (DrawingKind::ParallelChannel, [a, b, c]) => {
+                let offset = channel_offset(*a, *b, *c);
+                Some(vec![*a, *b, [b[0], b[1] + offset], [a[0], a[1] + offset]])
+            }
+            (DrawingKind::Text, [a]) => {
+                let rect = self.text_rect(plot_ui, *a);
+                Some(
+                    [
+                        rect.left_top(),
+                        rect.right_top(),
+                        rect.right_bottom(),
+                        rect.left_bottom(),
+                    ]
+                    .into_iter()
+                    .map(|pos| {
+                        let point = plot_ui.plot_from_screen(pos);
+                        [point.x, point.y]
+                    })
+                    .collect(),
+                )
+            }
+            _ => None,
+        }
+    }
+
+    /// 文字注释在屏幕上的范围，包括背景
+    fn text_rect(&self, plot_ui: &PlotUi, point: [f64; 2]) -> Rect {
+        let size = plot_ui.ctx().fonts(|fonts| {
+            fonts
+                .layout_no_wrap(
+                    self.text.to_owned(),
+                    FontId::proportional(self.style.font_size),
+                    self.style.color,
+                )
+                .size()
+        });
+        let pos = plot_ui.screen_from_plot(PlotPoint::from(point));
+        Rect::from_min_size(pos - Vec2::new(0.0, size.y), size).expand(TEXT_PADDING)
+    }
+
+    /// 图中的文字，包括斐波那契画线每条线的比例和价格(显示在右侧坐标轴处)和文字注释
+    fn labels(
+        &self,
+        points: &[[f64; 2]],
+        bounds: &PlotBounds,
+        fibonacci: &FibonacciSettings,
+        instrument: &Instrument,
+    ) -> Vec<Text> {
+        let right = bounds.max()[0];
+        let level_labels = |levels: Vec<(f64, f64, Color32)>, left: f64| {
+            levels
+                .into_iter()
+                .flat_map(|(value, price, color)| {
+                    [
+                        Text::new(PlotPoint::new(left, price), value.to_string())
+                            .color(color)
+                            .anchor(Align2::LEFT_BOTTOM),
+                        Text::new(PlotPoint::new(right, price), instrument.format_price(price))
+                            .color(color)
+                            .anchor(Align2::RIGHT_BOTTOM),
+                    ]
+                })
+                .collect()
+        };
+        match (self.kind, points) {
+            (DrawingKind::FibRetracement, [a, b]) => {
+                level_labels(fibonacci.retracement_levels(a[1], b[1]), a[0].min(b[0]))
+            }
+            (DrawingKind::FibExtension, [a, b, c]) => {
+                level_labels(fibonacci.extension_levels(a[1], b[1], c[1]), c[0])
+            }
+            (DrawingKind::FibTimeZones, [a, b]) => fibonacci
+                .time_zone_levels(a[0], b[0])
+                .into_iter()
+                .map(|(value, x, color)| {
+                    Text::new(PlotPoint::new(x, bounds.max()[1]), value.to_string())
+                        .color(color)
+                        .anchor(Align2::LEFT_TOP)
+                })
+                .collect(),
+            (DrawingKind::Text, [a]) => vec![Text::new(
+                PlotPoint::from(*a),
+                RichText::new(&self.text).size(self.style.font_size),
+            )
+            .color(self.style.color)
+            .anchor(Align2::LEFT_BOTTOM)],
+            _ => vec![],
+        }
+    }
+}
+
+/// 以a和b为外接矩形对角的椭圆
+fn ellipse(a: [f64; 2], b: [f64; 2]) -> Vec<[f64; 2]> {
+    let center = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+    let radius = [(b[0] - a[0]).abs() / 2.0, (b[1] - a[1]).abs() / 2.0];
+    (0..=ELLIPSE_SEGMENTS)
+        .map(|i| {
+            let angle = TAU * i as f64 / ELLIPSE_SEGMENTS as f64;
+            [
+                center[0] + radius[0] * angle.cos(),
+                center[1] + radius[1] * angle.sin(),
+            ]
+        })
+        .collect()
+}
+
+/// 平行通道中经过c的平行线相对a、b直线的价格偏移
+fn channel_offset(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    if b[0] != a[0] {
+        c[1] - (a[1] + (b[1] - a[1]) * (c[0] - a[0]) / (b[0] - a[0]))
+    } else {
+        c[1] - a[1]
+    }
+}
+
+/// 从a出发经过b，延伸到可见范围之外的点
+fn extend(a: [f64; 2], b: [f64; 2], bounds: &PlotBounds) -> [f64; 2] {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let center = bounds.center();
+    // 沿任意一个轴移出可见范围即可，移动的距离不小于跨度加上a到中心的距离
+    let scale = |d: f64, span: f64, distance: f64| {
+        if d == 0.0 {
+            f64::INFINITY
+        } else {
+            (span + distance) / d.abs()
+        }
+    };
+    let t = scale(dx, bounds.width(), (a[0] - center.x).abs()).min(scale(
+        dy,
+        bounds.height(),
+        (a[1] - center.y).abs(),
+    ));
+    if t.is_finite() {
+        [a[0] + dx * t, a[1] + dy * t]
+    } else {
+        b
+    }
+}
+
+/// 点到线段的像素距离
+fn distance_to_segment(point: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_sq() > 0.0 {
+        ((point - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (a + ab * t).distance(point)
+}
+
+/// 点是否在多边形内
+fn polygon_contains(polygon: &[Pos2], point: Pos2) -> bool {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .filter(|(a, b)| {
+            (a.y > point.y) != (b.y > point.y)
+                && point.x < a.x + (b.x - a.x) * (point.y - a.y) / (b.y - a.y)
+        })
+        .count()
+        % 2
+        == 1
+}
+
+/// 正在拖动的画线
+#[derive(Debug, Clone)]
+struct DragTarget {
+    index: usize,
+    /// 拖动的锚点，None时整体移动
+    anchor: Option<usize>,
+    /// 是否是刚创建的画线
+    creating: bool,
+    /// 锚点是否在松开按键后继续跟随指针，直到下一次点击
+    follow: bool,
+    /// 上一帧指针在图中的位置
+    last: PlotPoint,
+    /// 拖动前的画线，放下时与它比较记录修改
+    original: Drawing,
+}
+
+/// 导出的画线，可以分享给其他人导入
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct DrawingsExport {
+    /// 画线所属的合约代码
+    code: String,
+    /// 画线所属的周期
+    ktype: String,
+    drawings: Vec<Drawing>,
+}
+
+/// saved中合约和周期对应的key
+fn drawings_key(code: &str, ktype: &str) -> String {
+    format!("{}/{}", code, ktype)
+}
+
+/// 蜡烛图上的画线，按合约和周期分别保存，会随App一起存储
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Drawings {
+    /// 当前合约和周期的画线
+    pub items: Vec<Drawing>,
+    /// items所属的合约代码
+    code: String,
+    /// items所属的周期
+    ktype: String,
+    /// 其他合约和周期的画线，切换回来时恢复
+    saved: BTreeMap<String, Vec<Drawing>>,
+    /// 新画线的样式
+    pub style: DrawingStyle,
+    /// 斐波那契画线的设置
+    pub fibonacci: FibonacciSettings,
+    /// 当前的画线工具，None时可以选择和拖动已有的画线
+    #[serde(skip)]
+    tool: Option<DrawingKind>,
+    /// 选中的画线
+    #[serde(skip)]
+    selected: Option<usize>,
+    #[serde(skip)]
+    dragging: Option<DragTarget>,
+    /// 最近一次按下指针是否用于画线，是的话拖动时不移动图
+    #[serde(skip)]
+    pointer_captured: bool,
+    /// 画线属性窗口是否打开
+    #[serde(skip)]
+    properties_open: bool,
+    /// 导入导出窗口是否打开
+    #[serde(skip)]
+    share_open: bool,
+    /// 导入导出用的JSON文本
+    #[serde(skip)]
+    json: String,
+    /// 导入导出的结果
+    #[serde(skip)]
+    json_message: Option<String>,
+    /// 还没有记录到撤销历史的修改
+    #[serde(skip)]
+    commands: Vec<ChartCommand>,
+}
+
+impl Drawings {
+    /// 拖动图时是否应该由画线处理
+    pub fn pointer_captured(&self) -> bool {
+        self.pointer_captured
+    }
+
+    /// 取出这一帧的修改，由图表记录到撤销历史
+    pub fn take_commands(&mut self) -> Vec<ChartCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn record(&mut self, edit: ListEdit<Drawing>) {
+        self.commands.push(ChartCommand::Drawing(Box::new(edit)));
+    }
+
+    /// 撤销或重做当前合约和周期画线的修改，正在进行的拖动会被取消
+    pub fn apply(&mut self, edit: &ListEdit<Drawing>, undo: bool) {
+        self.cancel_dragging();
+        self.selected = None;
+        edit.apply(&mut self.items, undo);
+    }
+
+    /// 取消拖动，正在创建的画线被删除，移动中的画线回到原来的位置
+    fn cancel_dragging(&mut self) {
+        let Some(target) = self.dragging.take() else {
+            return;
+        };
+        if target.creating {
+            if target.index < self.items.len() {
+                self.items.remove(target.index);
+            }
+            self.selected = None;
+        } else if let Some(drawing) = self.items.get_mut(target.index) {
+            *drawing = target.original;
+        }
+    }
+
+    /// 切换到code合约ktype周期的画线，当前的画线保存起来
+    pub fn switch(&mut self, code: &str, ktype: &str) {
+        if self.code == code && self.ktype == ktype {
+            return;
+        }
+        let items = std::mem::take(&mut self.items);
+        if !items.is_empty() {
+            self.saved
+                .insert(drawings_key(&self.code, &self.ktype), items);
+        }
+        self.items = self
+            .saved
+            .remove(&drawings_key(code, ktype))
+            .unwrap_or_default();
+        self.code = code.to_owned();
+        self.ktype = ktype.to_owned();
+        self.selected = None;
+        self.dragging = None;
+        self.tool = None;
+    }
+
+    /// 工具栏中的画线工具、属性和删除按钮
+    pub fn show_toolbar(&mut self, ui: &mut Ui) {
+        ComboBox::from_id_source("drawing_tool")
+            .selected_text(self.tool.map_or(t("画线"), |tool| tool.name()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.tool, None, t("光标"));
+                DrawingKind::ALL.iter().for_each(|kind| {
+                    ui.selectable_value(&mut self.tool, Some(*kind), kind.name());
+                });
+            });
+        ui.toggle_value(&mut self.properties_open, t("属性"))
+            .on_hover_text(t("双击画线也可以打开属性"));
+        if ui
+            .add_enabled(self.selected.is_some(), Button::new(t("删除")))
+            .on_hover_text(t("删除选中的画线"))
+            .clicked()
+        {
+            self.delete_selected();
+        }
+        ui.toggle_value(&mut self.fibonacci.open, t("斐波那契"));
+        ui.toggle_value(&mut self.share_open, t("分享画线"));
+    }
+
+    /// 显示画线导入导出窗口，导出当前合约和周期的画线，导入到画线所属的合约和周期
+    pub fn show_share_window(&mut self, ctx: &Context) {
+        let mut open = self.share_open;
+        Window::new(t("分享画线"))
+            .id(Id::new("drawings_share"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button(t("导出")).clicked() {
+                        let export = DrawingsExport {
+                            code: self.code.to_owned(),
+                            ktype: self.ktype.to_owned(),
+                            drawings: self.items.to_owned(),
+                        };
+                        match serde_json::to_string_pretty(&export) {
+                            Ok(json) => {
+                                ui.output_mut(|output| output.copied_text = json.to_owned());
+                                self.json = json;
+                                self.json_message = Some(t("已复制到剪贴板").to_string());
+                            }
+                            Err(err) => self.json_message = Some(err.to_string()),
+                        }
+                    }
+                    if ui.button(t("导入")).clicked() {
+                        self.json_message = Some(match self.import(&self.json.to_owned()) {
+                            Ok(message) => message,
+                            Err(err) => err.to_string(),
+                        });
+                    }
+                });
+                ui.add(
+                    TextEdit::multiline(&mut self.json)
+                        .code_editor()
+                        .desired_rows(6)
+                        .hint_text(t("粘贴画线JSON后点击导入")),
+                );
+                if let Some(message) = &self.json_message {
+                    ui.label(message);
+                }
+            });
+        self.share_open = open;
+    }
+
+    /// 导入画线，添加到已有的画线之后
+    fn import(&mut self, json: &str) -> Result<String, serde_json::Error> {
+        let export = serde_json::from_str::<DrawingsExport>(json)?;
+        let count = export.drawings.len();
+        // 没有合约代码或周期时导入到当前的合约或周期
+        let code = if export.code.is_empty() {
+            self.code.to_owned()
+        } else {
+            export.code
+        };
+        let ktype = if export.ktype.is_empty() {
+            self.ktype.to_owned()
+        } else {
+            export.ktype
+        };
+        if code == self.code && ktype == self.ktype {
+            self.items.extend(export.drawings);
+        } else {
+            self.saved
+                .entry(drawings_key(&code, &ktype))
+                .or_default()
+                .extend(export.drawings);
+        }
+        Ok(tf(
+            "已导入{}条画线到{}",
+            &[&count, &format!("{} {}", code, ktype)],
+        ))
+    }
+
+    /// 显示画线属性窗口，有选中的画线时修改它，否则修改新画线的样式
+    pub fn show_window(&mut self, ctx: &Context) {
+        let mut open = self.properties_open;
+        let mut delete = false;
+        Window::new(t("画线属性"))
+            .id(Id::new("drawing_properties"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                // 正在创建的画线完成时才记录，属性的修改包含在其中
+                let creating = self
+                    .dragging
+                    .as_ref()
+                    .filter(|target| target.creating)
+                    .map(|target| target.index);
+                match self
+                    .selected
+                    .and_then(|index| Some((index, self.items.get_mut(index)?)))
+                {
+                    Some((index, drawing)) => {
+                        let before = drawing.to_owned();
+                        ui.strong(drawing.kind.name());
+                        if drawing.kind == DrawingKind::Text {
+                            ui.add(TextEdit::multiline(&mut drawing.text).desired_rows(3));
+                        }
+                        drawing.style.show_ui(ui);
+                        delete = ui.button(t("删除")).clicked();
+                        if *drawing != before && creating != Some(index) {
+                            self.commands
+                                .push(ChartCommand::Drawing(Box::new(ListEdit::Update(
+                                    index,
+                                    before,
+                                    drawing.to_owned(),
+                                ))));
+                        }
+                    }
+                    None => {
+                        let before = self.style.to_owned();
+                        ui.strong(t("新画线的样式"));
+                        self.style.show_ui(ui);
+                        if self.style != before {
+                            self.commands
+                                .push(ChartCommand::DrawingStyle(before, self.style.to_owned()));
+                        }
+                    }
+                }
+            });
+        if delete {
+            self.delete_selected();
+        }
+        self.properties_open = open;
+    }
+
+    fn delete_selected(&mut self) {
+        self.cancel_dragging();
+        if let Some(index) = self.selected.take() {
+            if index < self.items.len() {
+                let drawing = self.items.remove(index);
+                self.record(ListEdit::Remove(index, drawing));
+            }
+        }
+    }
+
+    /// 处理画线的创建、选择、拖动和删除，并绘制所有画线
+    ///
+    /// snap将指针位置吸附到k线或最小变动价位，用于放置和拖动锚点
+    pub fn show(
+        &mut self,
+        plot_ui: &mut PlotUi,
+        real_datas: &[RealData],
+        instrument: &Instrument,
+        snap: impl Fn(&PlotUi, PlotPoint) -> PlotPoint,
+    ) {
+        self.handle_input(plot_ui, real_datas, snap);
+        let bounds = plot_ui.plot_bounds();
+        self.items.iter().enumerate().for_each(|(index, drawing)| {
+            let points = drawing.points(real_datas);
+            let fill = drawing.style.fill;
+            if let Some(polygon) = drawing.fill(&points, plot_ui).filter(|_| fill.a() > 0) {
+                plot_ui.polygon(
+                    Polygon::new(polygon)
+                        .stroke(Stroke::new(0.0, fill))
+                        .fill_alpha(fill.a() as f32 / 255.0),
+                );
+            }
+            drawing
+                .polylines(&points, plot_ui, &self.fibonacci)
+                .into_iter()
+                .for_each(|polyline| {
+                    plot_ui.line(
+                        Line::new(polyline.points)
+                            .color(polyline.color)
+                            .width(drawing.style.width)
+                            .style(polyline.style),
+                    );
+                });
+            drawing
+                .labels(&points, &bounds, &self.fibonacci, instrument)
+                .into_iter()
+                .for_each(|text| plot_ui.text(text));
+            if self.selected == Some(index) {
+                plot_ui.points(
+                    Points::new(points)
+                        .color(drawing.style.color)
+                        .radius(4.0)
+                        .filled(true),
+                );
+            }
+        });
+    }
+
+    fn handle_input(
+        &mut self,
+        plot_ui: &PlotUi,
+        real_datas: &[RealData],
+        snap: impl Fn(&PlotUi, PlotPoint) -> PlotPoint,
+    ) {
+        let (pressed, down, double_clicked, pointer, delete, escape) =
+            plot_ui.ctx().input(|input| {
+                (
+                    input.pointer.primary_pressed(),
+                    input.pointer.primary_down(),
+                    input.pointer.button_double_clicked(PointerButton::Primary),
+                    input.pointer.interact_pos(),
+                    input.key_pressed(Key::Delete),
+                    input.key_pressed(Key::Escape),
+                )
+            });
+        // 双击画线打开属性窗口
+        if double_clicked && self.selected.is_some() && plot_ui.plot_hovered() {
+            self.properties_open = true;
+        }
+        // 输入框有焦点时按键用于编辑文字
+        if plot_ui.ctx().memory(|memory| memory.focus().is_none()) {
+            if delete {
+                self.delete_selected();
+            }
+            if escape {
+                self.cancel_dragging();
+                self.tool = None;
+                self.selected = None;
+            }
+        }
+        let Some(pointer) = pointer else {
+            return;
+        };
+        let point = plot_ui.plot_from_screen(pointer);
+        // 整体移动时使用原始位置，放置锚点时使用吸附后的位置
+        let snapped = snap(plot_ui, point);
+        if pressed {
+            self.pointer_captured = false;
+        }
+        if let Some(target) = self.dragging.as_mut() {
+            let Some(drawing) = self.items.get_mut(target.index) else {
+                self.dragging = None;
+                return;
+            };
+            if down || target.follow {
+                let (dx, dy) = (point.x - target.last.x, point.y - target.last.y);
+                target.last = point;
+                drawing
+                    .anchors
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(index, _)| target.anchor.is_none() || target.anchor == Some(*index))
+                    .for_each(|(_, anchor)| {
+                        let moved = match (target.anchor, anchor.to_plot(real_datas)) {
+                            (Some(_), _) => Anchor::from_plot(real_datas, snapped.x, snapped.y),
+                            (None, Some([x, price])) => {
+                                Anchor::from_plot(real_datas, x + dx, price + dy)
+                            }
+                            (None, None) => None,
+                        };
+                        if let Some(moved) = moved {
+                            *anchor = moved;
+                        }
+                    });
+            }
+            // 拖动时松开按键或跟随时点击，放置当前的锚点
+            let placed = if target.follow { pressed } else { !down };
+            if !placed {
+                return;
+            }
+            if pressed {
+                self.pointer_captured = true;
+            }
+            // 刚创建的画线依次放置锚点，只点击没有拖动时锚点跟随指针，再次点击时放置
+            let anchor = target.anchor.unwrap_or(0);
+            let degenerate = anchor > 0 && drawing.anchors[anchor] == drawing.anchors[anchor - 1];
+            if target.creating && degenerate {
+                if target.follow {
+                    self.items.remove(target.index);
+                    self.selected = None;
+                    self.dragging = None;
+                } else {
+                    target.follow = true;
+                }
+                return;
+            }
+            if target.creating && anchor + 1 < drawing.anchors.len() {
+                drawing.anchors[anchor + 1] = drawing.anchors[anchor].to_owned();
+                target.anchor = Some(anchor + 1);
+                target.follow = true;
+                return;
+            }
+            // 新画线放下最后一个锚点时完成创建，已有的画线有移动时记录修改
+            let edit = if target.creating {
+                Some(ListEdit::Add(target.index, drawing.to_owned()))
+            } else {
+                (*drawing != target.original).then(|| {
+                    ListEdit::Update(target.index, target.original.to_owned(), drawing.to_owned())
+                })
+            };
+            self.dragging = None;
+            if let Some(edit) = edit {
+                self.record(edit);
+            }
+            if pressed {
+                return;
+            }
+        }
+        // 按住Shift或Alt拖动时由测量工具或区间选择处理
+        let modified = plot_ui
+            .ctx()
+            .input(|input| input.modifiers.shift || input.modifiers.alt);
+        if !pressed || modified || !plot_ui.plot_hovered() {
+            return;
+        }
+        // 画完一条线后回到选择状态
+        match self.tool.take() {
+            Some(kind) => {
+                let Some(anchor) = Anchor::from_plot(real_datas, snapped.x, snapped.y) else {
+                    return;
+                };
+                self.items.push(Drawing {
+                    kind,
+                    anchors: vec![anchor; kind.anchor_count()],
+                    style: self.style.to_owned(),
+                    text: t("注释").to_string(),
+                });
+                // 文字注释创建后直接编辑内容
+                if kind == DrawingKind::Text {
+                    self.properties_open = true;
+                }
+                let index = self.items.len() - 1;
+                self.selected = Some(index);
+                // 先拖动第二个锚点，单锚点的画线拖动第一个
+                self.dragging = Some(DragTarget {
+                    index,
+                    anchor: Some(kind.anchor_count().min(2) - 1),
+                    creating: true,
+                    follow: false,
+                    last: point,
+                    original: self.items[index].to_owned(),
+                });
+            }
+            None => {
+                let hit = self.hit_test(plot_ui, real_datas, pointer);
+                self.selected = hit.map(|(index, _)| index);
+                self.dragging = hit.map(|(index, anchor)| DragTarget {
+                    index,
+                    anchor,
+                    creating: false,
+                    follow: false,
+                    last: point,
+                    original: self.items[index].to_owned(),
+                });
+            }
+        }
+        self.pointer_captured = self.dragging.is_some();
+    }
+
+    /// 找到指针处的画线和锚点，后画的在上层，优先选中
+    fn hit_test(
+        &self,
+        plot_ui: &PlotUi,
+        real_datas: &[RealData],
+        pointer: Pos2,
+    ) -> Option<(usize, Option<usize>)> {
+        let to_screen = |point: &[f64; 2]| plot_ui.screen_from_plot(PlotPoint::from(*point));
+        self.items
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, drawing)| {
+                let points = drawing.points(real_datas);
+                if let Some(anchor) = points
+                    .iter()
+                    .position(|point| to_screen(point).distance(pointer) <= HIT_DISTANCE)
+                {
+                    return Some((index, Some(anchor)));
+                }
+                let on_line = drawing
+                    .polylines(&points, plot_ui, &self.fibonacci)
+                    .iter()
+                    .any(|polyline| {
+                        polyline.points.windows(2).any(|segment| {
+                            distance_to_segment(
+                                pointer,
+                                to_screen(&segment[0]),
+                                to_screen(&segment[1]),
+                            ) <= HIT_DISTANCE
+                        })
+                    });
+                // 有填充的区域和文字注释点击内部也可以选中
+                let inside = drawing
+                    .fill(&points, plot_ui)
+                    .filter(|_| drawing.style.fill.a() > 0 || drawing.kind == DrawingKind::Text)
+                    .is_some_and(|polygon| {
+                        polygon_contains(
+                            &polygon.iter().map(to_screen).collect::<Vec<Pos2>>(),
+                            pointer,
+                        )
+                    });
+                (on_line || inside).then_some((index, None))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drawing(price: f64) -> Drawing {
+        Drawing {
+            anchors: vec![Anchor {
+                datetime: "2023-01-01T09:00".to_string(),
+                offset: 0.0,
+                price,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let drawing = serde_json::from_str::<Drawing>(r#"{"kind":"Rectangle"}"#).unwrap();
+        assert_eq!(drawing.kind, DrawingKind::Rectangle);
+        assert!(drawing.anchors.is_empty());
+        assert_eq!(drawing.style, DrawingStyle::default());
+        assert_eq!(drawing.text, "");
+    }
+
+    #[test]
+    fn switch_by_code_and_ktype() {
+        let mut drawings = Drawings::default();
+        drawings.switch("A", "m1");
+        drawings.items.push(drawing(1.0));
+        drawings.switch("A", "m5");
+        assert!(drawings.items.is_empty());
+        drawings.items.push(drawing(5.0));
+        drawings.switch("A", "m1");
+        assert_eq!(drawings.items, vec![drawing(1.0)]);
+        drawings.switch("A", "m5");
+        assert_eq!(drawings.items, vec![drawing(5.0)]);
+    }
+
+    #[test]
+    fn import_to_code_and_ktype() {
+        let mut drawings = Drawings::default();
+        drawings.switch("A", "m1");
+        let json = serde_json::to_string(&DrawingsExport {
+            code: "A".to_string(),
+            ktype: "m5".to_string(),
+            drawings: vec![drawing(5.0)],
+        })
+        .unwrap();
+        drawings.import(&json).unwrap();
+        assert!(drawings.items.is_empty());
+        drawings
+            .import(r#"{"code":"A","ktype":"m1","drawings":[{"kind":"TrendLine"}]}"#)
+            .unwrap();
+        assert_eq!(drawings.items.len(), 1);
+        drawings.switch("A", "m5");
+        assert_eq!(drawings.items, vec![drawing(5.0)]);
+    }
+}
@@ -0,0 +1,150 @@
+use egui::{Button, Color32, Context, DragValue, Grid, Id, Ui, Window};
+use serde::{Deserialize, Serialize};
+
+use super::i18n::t;
+
+/// 斐波那契工具中的一条线
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FibLevel {
+    /// 回撤和扩展为比例，时间周期为k线个数的倍数
+    pub value: f64,
+    pub color: Color32,
+    pub enabled: bool,
+}
+
+impl FibLevel {
+    fn new(value: f64, color: Color32) -> Self {
+        Self {
+            value,
+            color,
+            enabled: true,
+        }
+    }
+}
+
+/// 斐波那契回撤、扩展和时间周期的线，所有斐波那契画线共用，会随App一起存储
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FibonacciSettings {
+    pub retracement: Vec<FibLevel>,
+    pub extension: Vec<FibLevel>,
+    pub time_zones: Vec<FibLevel>,
+    /// 设置窗口是否打开
+    #[serde(skip)]
+    pub open: bool,
+}
+
+impl Default for FibonacciSettings {
+    fn default() -> Self {
+        Self {
+            retracement: Self::default_levels(&[0.0, 0.236, 0.382, 0.5, 0.618, 0.786, 1.0]),
+            extension: Self::default_levels(&[0.0, 0.618, 1.0, 1.272, 1.618, 2.0, 2.618]),
+            time_zones: [0.0, 1.0, 2.0, 3.0, 5.0, 8.0, 13.0, 21.0, 34.0, 55.0, 89.0]
+                .into_iter()
+                .map(|value| FibLevel::new(value, Color32::from_rgb(41, 98, 255)))
+                .collect(),
+            open: false,
+        }
+    }
+}
+
+impl FibonacciSettings {
+    /// 按顺序循环使用的默认颜色
+    const COLORS: [Color32; 7] = [
+        Color32::from_rgb(120, 123, 134),
+        Color32::from_rgb(242, 54, 69),
+        Color32::from_rgb(255, 152, 0),
+        Color32::from_rgb(76, 175, 80),
+        Color32::from_rgb(8, 153, 129),
+        Color32::from_rgb(0, 188, 212),
+        Color32::from_rgb(41, 98, 255),
+    ];
+
+    fn default_levels(values: &[f64]) -> Vec<FibLevel> {
+        values
+            .iter()
+            .zip(Self::COLORS.iter().cycle())
+            .map(|(value, color)| FibLevel::new(*value, *color))
+            .collect()
+    }
+
+    /// 回撤线的(比例, 价格, 颜色)，0在b处，1在a处
+    pub fn retracement_levels(&self, a: f64, b: f64) -> Vec<(f64, f64, Color32)> {
+        enabled(&self.retracement)
+            .map(|level| (level.value, b - (b - a) * level.value, level.color))
+            .collect()
+    }
+
+    /// 扩展线的(比例, 价格, 颜色)，从c开始按a到b的幅度延伸
+    pub fn extension_levels(&self, a: f64, b: f64, c: f64) -> Vec<(f64, f64, Color32)> {
+        enabled(&self.extension)
+            .map(|level| (level.value, c + (b - a) * level.value, level.color))
+            .collect()
+    }
+
+    /// 时间周期线的(倍数, x坐标, 颜色)，以a到b的k线个数为单位
+    pub fn time_zone_levels(&self, a: f64, b: f64) -> Vec<(f64, f64, Color32)> {
+        enabled(&self.time_zones)
+            .map(|level| (level.value, a + (b - a) * level.value, level.color))
+            .collect()
+    }
+
+    /// 显示斐波那契设置窗口
+    pub fn show_window(&mut self, ctx: &Context) {
+        let mut open = self.open;
+        Window::new(t("斐波那契"))
+            .id(Id::new("fibonacci_settings"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.collapsing(t("斐波那契回撤"), |ui| {
+                    levels_ui(ui, "fibonacci_retracement", &mut self.retracement);
+                });
+                ui.collapsing(t("斐波那契扩展"), |ui| {
+                    levels_ui(ui, "fibonacci_extension", &mut self.extension);
+                });
+                ui.collapsing(t("斐波那契时间周期"), |ui| {
+                    levels_ui(ui, "fibonacci_time_zones", &mut self.time_zones);
+                });
+                if ui.button(t("重置")).clicked() {
+                    *self = Self {
+                        open: true,
+                        ..Default::default()
+                    };
+                }
+            });
+        self.open = open;
+    }
+}
+
+fn enabled(levels: &[FibLevel]) -> impl Iterator<Item = &FibLevel> {
+    levels.iter().filter(|level| level.enabled)
+}
+
+/// 一组线的设置项，可以修改、删除和添加
+fn levels_ui(ui: &mut Ui, id: &str, levels: &mut Vec<FibLevel>) {
+    let mut removed = None;
+    Grid::new(id).show(ui, |ui| {
+        levels.iter_mut().enumerate().for_each(|(index, level)| {
+            ui.checkbox(&mut level.enabled, "");
+            ui.add(
+                DragValue::new(&mut level.value)
+                    .speed(0.001)
+                    .max_decimals(3),
+            );
+            ui.color_edit_button_srgba(&mut level.color);
+            if ui.add(Button::new(t("删除")).small()).clicked() {
+                removed = Some(index);
+            }
+            ui.end_row();
+        });
+    });
+    if let Some(index) = removed {
+        levels.remove(index);
+    }
+    if ui.button(t("添加")).clicked() {
+        let value = levels.last().map_or(0.0, |level| level.value + 1.0);
+        let color = FibonacciSettings::COLORS[levels.len() % FibonacciSettings::COLORS.len()];
+        levels.push(FibLevel::new(value, color));
+    }
+}
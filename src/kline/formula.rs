@@ -0,0 +1,773 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::{
+    i18n::{t, tf},
+    indicator::sma,
+    real_data::Candle,
+    utils::CustomError,
+};
+
+/// 内置的价格变量
+const PRICE_VARIABLES: [&str; 11] = [
+    "O", "OPEN", "H", "HIGH", "L", "LOW", "C", "CLOSE", "V", "VOL", "VOLUME",
+];
+
+/// 内置函数和参数个数
+const FUNCTIONS: [(&str, usize); 12] = [
+    ("MA", 2),
+    ("EMA", 2),
+    ("REF", 2),
+    ("HHV", 2),
+    ("LLV", 2),
+    ("SUM", 2),
+    ("STD", 2),
+    ("CROSS", 2),
+    ("ABS", 1),
+    ("MAX", 2),
+    ("MIN", 2),
+    ("IF", 3),
+];
+
+/// 第二个参数必须是数字的函数
+const PERIOD_FUNCTIONS: [&str; 7] = ["MA", "EMA", "REF", "HHV", "LLV", "SUM", "STD"];
+
+/// 每根k线对应一个值，数据不足时为None
+type Series = Vec<Option<f64>>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Operator(String),
+    LeftParen,
+    RightParen,
+    Comma,
+    Semicolon,
+    /// 输出变量，例如`DIF:EMA(C,12)-EMA(C,26)`
+    Colon,
+    /// 中间变量，例如`A:=MA(C,5)`
+    Assign,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(f64),
+    Variable(String),
+    Negative(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(String, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    /// 结果是否为条件(0或1)，条件输出作为信号绘制
+    fn is_condition(&self) -> bool {
+        match self {
+            Expr::Not(_) => true,
+            Expr::Binary(op, _, _) => matches!(
+                op.as_str(),
+                ">" | "<" | ">=" | "<=" | "=" | "<>" | "AND" | "OR"
+            ),
+            Expr::Call(name, _) => name == "CROSS",
+            _ => false,
+        }
+    }
+}
+
+/// 公式中的一条语句
+#[derive(Debug, Clone)]
+struct Statement {
+    name: Option<String>,
+    /// 是否需要绘制
+    output: bool,
+    expr: Expr,
+}
+
+/// 公式的一个输出
+#[derive(Debug, Clone)]
+pub struct FormulaOutput {
+    pub name: String,
+    pub values: Vec<Option<f64>>,
+    /// 是否为信号，信号的值为0或1
+    pub is_signal: bool,
+}
+
+/// 通达信/文华风格的指标公式，例如`MA(C,5)-MA(C,20)`
+///
+/// 语句之间用`;`分隔，`名称:表达式`为输出，`名称:=表达式`为中间变量，没有名称的表达式也会输出。
+#[derive(Debug, Clone)]
+pub struct Formula {
+    statements: Vec<Statement>,
+}
+
+impl Formula {
+    /// 解析公式，出错时返回带位置的错误信息
+    pub fn parse(source: &str) -> Result<Self, CustomError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            variables: PRICE_VARIABLES
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        };
+        let mut statements = vec![];
+        while parser.peek().is_some() {
+            if parser.eat(&Token::Semicolon) {
+                continue;
+            }
+            statements.push(parser.statement()?);
+            if parser.peek().is_some() && !parser.eat(&Token::Semicolon) {
+                return Err(parser.error(t("缺少分号")));
+            }
+        }
+        if !statements.iter().any(|statement| statement.output) {
+            return Err(CustomError::Formula(t("公式没有输出").to_string()));
+        }
+        Ok(Self { statements })
+    }
+
+    /// 在k线数据上计算公式的所有输出
+    pub fn evaluate(&self, candles: &[Candle]) -> Vec<FormulaOutput> {
+        let mut variables: HashMap<String, Series> = HashMap::new();
+        let mut outputs = vec![];
+        self.statements
+            .iter()
+            .enumerate()
+            .for_each(|(index, statement)| {
+                let values = evaluate(&statement.expr, candles, &variables);
+                if statement.output {
+                    outputs.push(FormulaOutput {
+                        name: statement
+                            .name
+                            .to_owned()
+                            .unwrap_or_else(|| format!("F{}", index + 1)),
+                        values: values.to_owned(),
+                        is_signal: statement.expr.is_condition(),
+                    });
+                }
+                if let Some(name) = &statement.name {
+                    variables.insert(name.to_owned(), values);
+                }
+            });
+        outputs
+    }
+}
+
+/// 公式的解析结果，源码不变时不再重新解析
+#[derive(Debug, Clone, Default)]
+pub struct FormulaCache {
+    source: String,
+    parsed: Option<Arc<Result<Formula, CustomError>>>,
+}
+
+impl FormulaCache {
+    /// 源码修改后重新解析
+    pub fn update(&mut self, source: &str) {
+        if self.parsed.is_none() || self.source != source {
+            self.source = source.to_owned();
+            self.parsed = Some(Arc::new(Formula::parse(source)));
+        }
+    }
+
+    /// 最近一次解析的结果，还没有解析时为None
+    pub fn get(&self) -> Option<&Result<Formula, CustomError>> {
+        self.parsed.as_deref()
+    }
+}
+
+/// 词法分析，返回每个记号和它在源码中的字符位置
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, CustomError> {
+    let chars = source.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text = chars[start..i].iter().collect::<String>();
+            let number = text
+                .parse::<f64>()
+                .map_err(|_| position_error(start, &tf("无效的数字{}", &[&text])))?;
+            tokens.push((Token::Number(number), start));
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let ident = chars[start..i].iter().collect::<String>().to_uppercase();
+            let token = match ident.as_str() {
+                "AND" | "OR" | "NOT" => Token::Operator(ident),
+                _ => Token::Ident(ident),
+            };
+            tokens.push((token, start));
+            continue;
+        }
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match (c, next) {
+            (':', Some('=')) => (Token::Assign, 2),
+            (':', _) => (Token::Colon, 1),
+            ('>', Some('=')) | ('<', Some('=')) | ('<', Some('>')) | ('!', Some('=')) => {
+                let op = if c == '!' {
+                    "<>".to_string()
+                } else {
+                    format!("{}{}", c, next.unwrap_or_default())
+                };
+                (Token::Operator(op), 2)
+            }
+            ('=', Some('=')) => (Token::Operator("=".to_string()), 2),
+            ('&', Some('&')) => (Token::Operator("AND".to_string()), 2),
+            ('|', Some('|')) => (Token::Operator("OR".to_string()), 2),
+            ('+', _) | ('-', _) | ('*', _) | ('/', _) | ('>', _) | ('<', _) | ('=', _) => {
+                (Token::Operator(c.to_string()), 1)
+            }
+            ('(', _) => (Token::LeftParen, 1),
+            (')', _) => (Token::RightParen, 1),
+            (',', _) => (Token::Comma, 1),
+            (';', _) => (Token::Semicolon, 1),
+            _ => return Err(position_error(start, &tf("无法识别的字符'{}'", &[&c]))),
+        };
+        tokens.push((token, start));
+        i += len;
+    }
+    Ok(tokens)
+}
+
+fn position_error(position: usize, message: &str) -> CustomError {
+    CustomError::Formula(tf("第{}个字符: {}", &[&(position + 1), &message]))
+}
+
+/// 递归下降的语法分析器
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    /// 已经定义的变量，使用未定义的变量会报错
+    variables: Vec<String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_operator(&mut self, operators: &[&str]) -> Option<String> {
+        match self.peek() {
+            Some(Token::Operator(op)) if operators.contains(&op.as_str()) => {
+                let op = op.to_owned();
+                self.position += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    /// 当前位置的错误，已经到结尾时指向最后一个记号之后
+    fn error(&self, message: &str) -> CustomError {
+        match self.tokens.get(self.position) {
+            Some((_, position)) => position_error(*position, message),
+            None => CustomError::Formula(tf("公式结尾: {}", &[&message])),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, CustomError> {
+        if let (Some(Token::Ident(name)), Some((next, _))) =
+            (self.peek().cloned(), self.tokens.get(self.position + 1))
+        {
+            if *next == Token::Colon || *next == Token::Assign {
+                // 计算时价格变量优先，重新定义不会生效
+                if PRICE_VARIABLES.contains(&name.as_str()) {
+                    return Err(self.error(&tf("{}是内置变量，不能重新定义", &[&name])));
+                }
+                let output = *next == Token::Colon;
+                self.position += 2;
+                let expr = self.or()?;
+                self.variables.push(name.to_owned());
+                return Ok(Statement {
+                    name: Some(name),
+                    output,
+                    expr,
+                });
+            }
+        }
+        Ok(Statement {
+            name: None,
+            output: true,
+            expr: self.or()?,
+        })
+    }
+
+    fn or(&mut self) -> Result<Expr, CustomError> {
+        let mut left = self.and()?;
+        while let Some(op) = self.eat_operator(&["OR"]) {
+            left = Expr::Binary(op, Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, CustomError> {
+        let mut left = self.not()?;
+        while let Some(op) = self.eat_operator(&["AND"]) {
+            left = Expr::Binary(op, Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    /// NOT的优先级低于比较运算，`NOT C>O`等同于`NOT (C>O)`
+    fn not(&mut self) -> Result<Expr, CustomError> {
+        if self.eat_operator(&["NOT"]).is_some() {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, CustomError> {
+        let left = self.additive()?;
+        match self.eat_operator(&[">", "<", ">=", "<=", "=", "<>"]) {
+            Some(op) => Ok(Expr::Binary(op, Box::new(left), Box::new(self.additive()?))),
+            None => Ok(left),
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expr, CustomError> {
+        let mut left = self.multiplicative()?;
+        while let Some(op) = self.eat_operator(&["+", "-"]) {
+            left = Expr::Binary(op, Box::new(left), Box::new(self.multiplicative()?));
+        }
+        Ok(left)
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, CustomError> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.eat_operator(&["*", "/"]) {
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CustomError> {
+        if self.eat_operator(&["-"]).is_some() {
+            return Ok(Expr::Negative(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, CustomError> {
+        let error = self.error(t("缺少表达式"));
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expr::Number(number)),
+            Some(Token::LeftParen) => {
+                let expr = self.or()?;
+                if !self.eat(&Token::RightParen) {
+                    return Err(self.error(t("缺少右括号")));
+                }
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() == Some(&Token::LeftParen) {
+                    self.position -= 1;
+                    return self.call();
+                }
+                if !self.variables.contains(&name) {
+                    self.position -= 1;
+                    return Err(self.error(&tf("未定义的变量{}", &[&name])));
+                }
+                Ok(Expr::Variable(name))
+            }
+            _ => Err(error),
+        }
+    }
+
+    fn call(&mut self) -> Result<Expr, CustomError> {
+        let name_error = self.error(t("未知的函数"));
+        let name = match self.next() {
+            Some(Token::Ident(name)) => name,
+            _ => return Err(name_error),
+        };
+        let arity = match FUNCTIONS.iter().find(|(function, _)| *function == name) {
+            Some((_, arity)) => *arity,
+            None => return Err(name_error),
+        };
+        self.next();
+        let mut args = vec![];
+        if !self.eat(&Token::RightParen) {
+            loop {
+                args.push(self.or()?);
+                if self.eat(&Token::Comma) {
+                    continue;
+                }
+                if self.eat(&Token::RightParen) {
+                    break;
+                }
+                return Err(self.error(t("缺少逗号或右括号")));
+            }
+        }
+        if args.len() != arity {
+            self.position -= 1;
+            return Err(self.error(&tf("{}需要{}个参数", &[&name, &arity])));
+        }
+        if PERIOD_FUNCTIONS.contains(&name.as_str()) && !matches!(args[1], Expr::Number(_)) {
+            self.position -= 1;
+            return Err(self.error(&tf("{}的第2个参数必须是数字", &[&name])));
+        }
+        Ok(Expr::Call(name, args))
+    }
+}
+
+/// 计算表达式，返回每根k线的值
+fn evaluate(expr: &Expr, candles: &[Candle], variables: &HashMap<String, Series>) -> Series {
+    match expr {
+        Expr::Number(number) => vec![Some(*number); candles.len()],
+        Expr::Variable(name) => match name.as_str() {
+            "O" | "OPEN" => candles.iter().map(|candle| Some(candle.open)).collect(),
+            "H" | "HIGH" => candles.iter().map(|candle| Some(candle.high)).collect(),
+            "L" | "LOW" => candles.iter().map(|candle| Some(candle.low)).collect(),
+            "C" | "CLOSE" => candles.iter().map(|candle| Some(candle.close)).collect(),
+            "V" | "VOL" | "VOLUME" => candles.iter().map(|candle| Some(candle.volume)).collect(),
+            _ => variables
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| vec![None; candles.len()]),
+        },
+        Expr::Negative(expr) => map(&evaluate(expr, candles, variables), |value| -value),
+        Expr::Not(expr) => map(&evaluate(expr, candles, variables), |value| {
+            bool_value(value == 0.0)
+        }),
+        Expr::Binary(op, left, right) => {
+            let left = evaluate(left, candles, variables);
+            let right = evaluate(right, candles, variables);
+            left.iter()
+                .zip(right.iter())
+                .map(|(left, right)| {
+                    let (left, right) = (left.as_ref()?, right.as_ref()?);
+                    match op.as_str() {
+                        "+" => Some(left + right),
+                        "-" => Some(left - right),
+                        "*" => Some(left * right),
+                        "/" if *right == 0.0 => None,
+                        "/" => Some(left / right),
+                        ">" => Some(bool_value(left > right)),
+                        "<" => Some(bool_value(left < right)),
+                        ">=" => Some(bool_value(left >= right)),
+                        "<=" => Some(bool_value(left <= right)),
+                        "=" => Some(bool_value(left == right)),
+                        "<>" => Some(bool_value(left != right)),
+                        "AND" => Some(bool_value(*left != 0.0 && *right != 0.0)),
+                        "OR" => Some(bool_value(*left != 0.0 || *right != 0.0)),
+                        _ => None,
+                    }
+                })
+                .collect()
+        }
+        Expr::Call(name, args) => {
+            let values = args
+                .iter()
+                .map(|arg| evaluate(arg, candles, variables))
+                .collect::<Vec<Series>>();
+            let period = match args.get(1) {
+                Some(Expr::Number(number)) => number.max(0.0) as usize,
+                _ => 0,
+            };
+            match name.as_str() {
+                "MA" => runs(&values[0], |run| sma(run, period)),
+                "EMA" => ema(&values[0], period),
+                "REF" => (0..candles.len())
+                    .map(|i| i.checked_sub(period).and_then(|j| values[0][j]))
+                    .collect(),
+                "HHV" => window(&values[0], period, |window| {
+                    window
+                        .iter()
+                        .fold(f64::NEG_INFINITY, |acc, value| acc.max(*value))
+                }),
+                "LLV" => window(&values[0], period, |window| {
+                    window
+                        .iter()
+                        .fold(f64::INFINITY, |acc, value| acc.min(*value))
+                }),
+                "SUM" => window(&values[0], period, |window| window.iter().sum::<f64>()),
+                // 与通达信一致使用样本标准差，除以n-1
+                "STD" => window(&values[0], period, |window| {
+                    let mean = window.iter().sum::<f64>() / window.len() as f64;
+                    (window
+                        .iter()
+                        .map(|value| (value - mean).powi(2))
+                        .sum::<f64>()
+                        / (window.len() - 1).max(1) as f64)
+                        .sqrt()
+                }),
+                "CROSS" => (0..candles.len())
+                    .map(|i| {
+                        let prev = i.checked_sub(1)?;
+                        let (a0, b0) = (values[0][prev]?, values[1][prev]?);
+                        let (a1, b1) = (values[0][i]?, values[1][i]?);
+                        Some(bool_value(a0 <= b0 && a1 > b1))
+                    })
+                    .collect(),
+                "ABS" => map(&values[0], |value| value.abs()),
+                "MAX" => zip(&values[0], &values[1], |a, b| a.max(b)),
+                "MIN" => zip(&values[0], &values[1], |a, b| a.min(b)),
+                "IF" => (0..candles.len())
+                    .map(|i| {
+                        if values[0][i]? != 0.0 {
+                            values[1][i]
+                        } else {
+                            values[2][i]
+                        }
+                    })
+                    .collect(),
+                _ => vec![None; candles.len()],
+            }
+        }
+    }
+}
+
+fn bool_value(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn map(values: &Series, f: impl Fn(f64) -> f64) -> Series {
+    values.iter().map(|value| value.map(&f)).collect()
+}
+
+fn zip(a: &Series, b: &Series, f: impl Fn(f64, f64) -> f64) -> Series {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| Some(f((*a)?, (*b)?)))
+        .collect()
+}
+
+/// 滑动窗口计算，窗口内有None或数据不足时为None
+fn window(values: &Series, period: usize, f: impl Fn(&[f64]) -> f64) -> Series {
+    let period = period.max(1);
+    (0..values.len())
+        .map(|i| {
+            if i + 1 < period {
+                return None;
+            }
+            let window = values[i + 1 - period..=i]
+                .iter()
+                .copied()
+                .collect::<Option<Vec<f64>>>()?;
+            Some(f(&window))
+        })
+        .collect()
+}
+
+/// 对每一段连续有值的数据分别计算，段之间为None
+fn runs(values: &Series, f: impl Fn(&[f64]) -> Series) -> Series {
+    let mut result = Vec::with_capacity(values.len());
+    let mut run = vec![];
+    // 末尾补一个None，处理最后一段
+    values
+        .iter()
+        .chain(std::iter::once(&None))
+        .for_each(|value| match value {
+            Some(value) => run.push(*value),
+            None => {
+                if !run.is_empty() {
+                    result.append(&mut f(&run));
+                    run.clear();
+                }
+                result.push(None);
+            }
+        });
+    result.pop();
+    result
+}
+
+/// 指数移动平均，从第一个有值的位置开始计算
+///
+/// 与indicator::ema不同，通达信的EMA以第一个值作为初始值，从第一根k线开始就有输出，
+/// 例如MACD的DIF从第一根k线开始，DEA在DIF有值后立即开始
+fn ema(values: &Series, period: usize) -> Series {
+    let alpha = 2.0 / (period.max(1) as f64 + 1.0);
+    let mut prev: Option<f64> = None;
+    values
+        .iter()
+        .map(|value| {
+            let value = (*value)?;
+            prev = Some(match prev {
+                Some(prev) => prev + alpha * (value - prev),
+                None => value,
+            });
+            prev
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 收盘价为3,1,2,4,3的k线
+    fn candles() -> Vec<Candle> {
+        [(2.0, 3.0), (2.0, 1.0), (2.0, 2.0), (3.0, 4.0), (4.0, 3.0)]
+            .iter()
+            .enumerate()
+            .map(|(index, (open, close))| {
+                Candle::for_test(
+                    index as i64,
+                    *open,
+                    open.max(*close) + 1.0,
+                    open.min(*close) - 1.0,
+                    *close,
+                    100.0,
+                )
+            })
+            .collect()
+    }
+
+    /// 公式第一个输出的值
+    fn values(source: &str) -> Series {
+        Formula::parse(source).unwrap().evaluate(&candles())[0]
+            .values
+            .to_owned()
+    }
+
+    fn assert_series(actual: Series, expected: &[Option<f64>]) {
+        assert_eq!(actual.len(), expected.len());
+        actual
+            .iter()
+            .zip(expected.iter())
+            .for_each(|(actual, expected)| match (actual, expected) {
+                (Some(actual), Some(expected)) => assert!(
+                    (actual - expected).abs() < 1e-9,
+                    "{} != {}",
+                    actual,
+                    expected
+                ),
+                _ => assert_eq!(actual, expected),
+            });
+    }
+
+    fn error(source: &str) -> String {
+        match Formula::parse(source) {
+            Err(CustomError::Formula(message)) => message,
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(error("MA(C,5) MA(C,10)").contains("缺少分号"));
+        assert!(error("(C+O").contains("缺少右括号"));
+        assert!(error("MA(C,5").contains("缺少逗号或右括号"));
+        assert!(error("FOO(C,1)").contains("未知的函数"));
+        assert!(error("MA(C)").contains("MA需要2个参数"));
+        assert!(error("MA(C,O)").contains("MA的第2个参数必须是数字"));
+        assert!(error("C+X").contains("未定义的变量X"));
+        assert!(error("1.2.3").contains("无效的数字1.2.3"));
+        assert!(error("A:=C;").contains("公式没有输出"));
+        assert!(error("C:=O*2;C").contains("C是内置变量，不能重新定义"));
+        assert!(error("VOL:V").contains("VOL是内置变量，不能重新定义"));
+    }
+
+    #[test]
+    fn error_position() {
+        assert_eq!(error("C+X"), "第3个字符: 未定义的变量X");
+        assert!(error("C+").starts_with("公式结尾"));
+    }
+
+    #[test]
+    fn moving_averages() {
+        assert_series(
+            values("MA(C,3)"),
+            &[None, None, Some(2.0), Some(7.0 / 3.0), Some(3.0)],
+        );
+        assert_series(
+            values("EMA(C,3)"),
+            &[Some(3.0), Some(2.0), Some(2.0), Some(3.0), Some(3.0)],
+        );
+        // 前面没有值的位置不参与计算
+        assert_series(
+            values("MA(REF(C,1),2)"),
+            &[None, None, Some(2.0), Some(1.5), Some(3.0)],
+        );
+    }
+
+    #[test]
+    fn standard_deviation() {
+        // 样本标准差
+        assert_series(
+            values("STD(C,2)"),
+            &[
+                None,
+                Some(2f64.sqrt()),
+                Some(0.5f64.sqrt()),
+                Some(2f64.sqrt()),
+                Some(0.5f64.sqrt()),
+            ],
+        );
+    }
+
+    #[test]
+    fn reference_and_extremes() {
+        assert_series(
+            values("REF(C,1)"),
+            &[None, Some(3.0), Some(1.0), Some(2.0), Some(4.0)],
+        );
+        assert_series(
+            values("HHV(H,2)"),
+            &[None, Some(4.0), Some(3.0), Some(5.0), Some(5.0)],
+        );
+        assert_series(
+            values("LLV(L,2)"),
+            &[None, Some(0.0), Some(0.0), Some(1.0), Some(2.0)],
+        );
+    }
+
+    #[test]
+    fn conditions() {
+        assert_series(
+            values("CROSS(C,MA(C,2))"),
+            &[None, None, Some(1.0), Some(0.0), Some(0.0)],
+        );
+        assert_series(
+            values("IF(C>O,1,-1)"),
+            &[Some(1.0), Some(-1.0), Some(-1.0), Some(1.0), Some(-1.0)],
+        );
+    }
+
+    #[test]
+    fn outputs() {
+        let outputs = Formula::parse("A:=C*2;B:A+1;C>O")
+            .unwrap()
+            .evaluate(&candles());
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].name, "B");
+        assert!(!outputs[0].is_signal);
+        assert_series(
+            outputs[0].values.to_owned(),
+            &[Some(7.0), Some(3.0), Some(5.0), Some(9.0), Some(7.0)],
+        );
+        assert_eq!(outputs[1].name, "F3");
+        assert!(outputs[1].is_signal);
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+
+use super::{
+    brick::BrickSettings,
+    chart_style::ChartStyle,
+    drawing::{Drawing, DrawingStyle},
+    indicator::IndicatorConfig,
+    theme::{ChartTheme, ThemeMode},
+};
+
+/// 默认最多保存的撤销步数
+const HISTORY_CAPACITY: usize = 100;
+
+/// 撤销和重做的命令栈，超过容量时丢弃最早的命令
+#[derive(Debug, Clone)]
+pub struct History<C> {
+    undo: VecDeque<C>,
+    redo: Vec<C>,
+    capacity: usize,
+    /// 最近的命令是否还可以与新命令合并
+    merging: bool,
+}
+
+impl<C> Default for History<C> {
+    fn default() -> Self {
+        Self::new(HISTORY_CAPACITY)
+    }
+}
+
+impl<C> History<C> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            capacity,
+            merging: false,
+        }
+    }
+
+    /// 记录一个新命令，之前撤销的命令不能再重做
+    pub fn push(&mut self, command: C) {
+        self.redo.clear();
+        if self.capacity == 0 {
+            return;
+        }
+        if self.undo.len() == self.capacity {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(command);
+        self.merging = true;
+    }
+
+    /// 记录一个新命令，连续的修改中merge返回true时合并到最近的命令，不作为新的一步
+    pub fn push_or_merge(&mut self, command: C, merge: impl FnOnce(&mut C, &C) -> bool) {
+        if let Some(last) = self.undo.back_mut().filter(|_| self.merging) {
+            if merge(last, &command) {
+                return;
+            }
+        }
+        self.push(command);
+    }
+
+    /// 结束连续的修改，之后的命令不再与之前的命令合并
+    pub fn end_merge(&mut self) {
+        self.merging = false;
+    }
+
+    /// 撤销最近的命令，返回需要撤销的命令
+    pub fn undo(&mut self) -> Option<&C> {
+        self.merging = false;
+        let command = self.undo.pop_back()?;
+        self.redo.push(command);
+        self.redo.last()
+    }
+
+    /// 重做最近撤销的命令，返回需要重做的命令
+    pub fn redo(&mut self) -> Option<&C> {
+        self.merging = false;
+        let command = self.redo.pop()?;
+        self.undo.push_back(command);
+        self.undo.back()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.merging = false;
+    }
+}
+
+/// 列表中一项的修改，撤销和重做时按位置恢复
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListEdit<T> {
+    /// 在index处添加
+    Add(usize, T),
+    /// 删除index处的一项
+    Remove(usize, T),
+    /// 修改index处的一项，保存修改前后的值
+    Update(usize, T, T),
+}
+
+impl<T: Clone> ListEdit<T> {
+    /// 对items撤销或重做这个修改
+    pub fn apply(&self, items: &mut Vec<T>, undo: bool) {
+        match (self, undo) {
+            (ListEdit::Add(index, item), false) | (ListEdit::Remove(index, item), true) => {
+                items.insert((*index).min(items.len()), item.to_owned());
+            }
+            (ListEdit::Add(index, _), true) | (ListEdit::Remove(index, _), false) => {
+                if *index < items.len() {
+                    items.remove(*index);
+                }
+            }
+            (ListEdit::Update(index, before, after), _) => {
+                if let Some(item) = items.get_mut(*index) {
+                    *item = if undo { before } else { after }.to_owned();
+                }
+            }
+        }
+    }
+
+    /// 连续修改同一项时合并，保留最早的修改前的值
+    fn merge(&mut self, next: &ListEdit<T>) -> bool {
+        match (self, next) {
+            (ListEdit::Update(index, _, after), ListEdit::Update(next_index, _, next_after))
+                if index == next_index =>
+            {
+                *after = next_after.to_owned();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 可以撤销的图表修改，值的修改保存修改前后的值
+#[derive(Debug, Clone)]
+pub enum ChartCommand {
+    /// 画线的创建、移动、删除和样式修改
+    Drawing(Box<ListEdit<Drawing>>),
+    /// 新画线的样式
+    DrawingStyle(DrawingStyle, DrawingStyle),
+    /// 指标的添加、删除和参数修改
+    Indicator(Box<ListEdit<IndicatorConfig>>),
+    ChartStyle(ChartStyle, ChartStyle),
+    HeikinAshi(bool, bool),
+    Bricks(BrickSettings, BrickSettings),
+    ThemeMode(ThemeMode, ThemeMode),
+    /// 深色(true)或浅色主题的修改
+    Theme(bool, Box<ChartTheme>, Box<ChartTheme>),
+}
+
+impl ChartCommand {
+    /// 连续修改同一个对象时(如拖动数值或输入文字)合并为一步，返回是否已经合并
+    pub fn merge(&mut self, next: &ChartCommand) -> bool {
+        match (self, next) {
+            (ChartCommand::Drawing(edit), ChartCommand::Drawing(next)) => edit.merge(next),
+            (ChartCommand::Indicator(edit), ChartCommand::Indicator(next)) => edit.merge(next),
+            (ChartCommand::DrawingStyle(_, after), ChartCommand::DrawingStyle(_, next)) => {
+                *after = next.to_owned();
+                true
+            }
+            (ChartCommand::Bricks(_, after), ChartCommand::Bricks(_, next)) => {
+                *after = next.to_owned();
+                true
+            }
+            (ChartCommand::Theme(dark, _, after), ChartCommand::Theme(next_dark, _, next))
+                if dark == next_dark =>
+            {
+                *after = next.to_owned();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_in_order() {
+        let mut history = History::new(10);
+        history.push(1);
+        history.push(2);
+        history.push(3);
+        assert_eq!(history.undo(), Some(&3));
+        assert_eq!(history.undo(), Some(&2));
+        assert_eq!(history.redo(), Some(&2));
+        assert_eq!(history.redo(), Some(&3));
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo(), Some(&3));
+    }
+
+    #[test]
+    fn empty_history() {
+        let mut history = History::<i32>::new(10);
+        assert!(!history.can_undo());
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo(), None);
+    }
+
+    #[test]
+    fn push_clears_redo() {
+        let mut history = History::new(10);
+        history.push(1);
+        history.push(2);
+        history.undo();
+        assert!(history.can_redo());
+        history.push(3);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(), Some(&3));
+        assert_eq!(history.undo(), Some(&1));
+        assert_eq!(history.undo(), None);
+    }
+
+    #[test]
+    fn capacity_drops_oldest() {
+        let mut history = History::new(3);
+        (1..=5).for_each(|command| history.push(command));
+        assert_eq!(history.undo(), Some(&5));
+        assert_eq!(history.undo(), Some(&4));
+        assert_eq!(history.undo(), Some(&3));
+        assert_eq!(history.undo(), None);
+        // 重做后不会超过容量
+        (0..3).for_each(|_| {
+            history.redo();
+        });
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo(), Some(&5));
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let mut history = History::new(0);
+        history.push(1);
+        assert!(!history.can_undo());
+        assert_eq!(history.undo(), None);
+    }
+
+    #[test]
+    fn merge_only_while_merging() {
+        let merge = |last: &mut i32, next: &i32| {
+            *last += next;
+            true
+        };
+        let mut history = History::new(10);
+        history.push_or_merge(1, merge);
+        history.push_or_merge(2, merge);
+        history.end_merge();
+        history.push_or_merge(4, merge);
+        assert_eq!(history.undo(), Some(&4));
+        // 撤销后的修改是新的一步
+        history.push_or_merge(8, merge);
+        assert_eq!(history.undo(), Some(&8));
+        assert_eq!(history.undo(), Some(&3));
+        assert_eq!(history.undo(), None);
+    }
+
+    #[test]
+    fn list_edit_round_trip() {
+        let mut items = vec![1, 2, 3];
+        [
+            ListEdit::Add(1, 5),
+            ListEdit::Remove(0, 1),
+            ListEdit::Update(1, 2, 6),
+        ]
+        .iter()
+        .for_each(|edit| {
+            let before = items.to_owned();
+            edit.apply(&mut items, false);
+            let after = items.to_owned();
+            edit.apply(&mut items, true);
+            assert_eq!(items, before);
+            edit.apply(&mut items, false);
+            assert_eq!(items, after);
+        });
+        assert_eq!(items, vec![5, 6, 3]);
+    }
+
+    #[test]
+    fn merge_updates_of_same_item() {
+        let mut edit = ListEdit::Update(0, 1, 2);
+        assert!(edit.merge(&ListEdit::Update(0, 2, 3)));
+        assert_eq!(edit, ListEdit::Update(0, 1, 3));
+        assert!(!edit.merge(&ListEdit::Update(1, 2, 3)));
+        assert!(!edit.merge(&ListEdit::Add(0, 3)));
+        let mut command = ChartCommand::HeikinAshi(false, true);
+        assert!(!command.merge(&ChartCommand::HeikinAshi(true, false)));
+    }
+
+    #[test]
+    fn clear_removes_everything() {
+        let mut history = History::new(10);
+        history.push(1);
+        history.push(2);
+        history.undo();
+        history.clear();
+        assert!(!history.can_undo());
+        assert!(!history.can_redo());
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// 界面语言
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    ZhCn,
+    EnUs,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::ZhCn, Language::EnUs];
+
+    /// 语言自身的名称，不随界面语言变化
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::ZhCn => "简体中文",
+            Language::EnUs => "English",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::ZhCn => "zh-CN",
+            Language::EnUs => "en-US",
+        }
+    }
+}
+
+/// 当前的界面语言，每帧由KLine设置
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    match LANGUAGE.load(Ordering::Relaxed) {
+        1 => Language::EnUs,
+        _ => Language::ZhCn,
+    }
+}
+
+/// 翻译界面文字，以中文原文作为key，zh-CN直接返回原文，没有译文时也返回原文
+pub fn t(text: &'static str) -> &'static str {
+    let catalog = match language() {
+        Language::ZhCn => return text,
+        Language::EnUs => EN_US,
+    };
+    catalog
+        .iter()
+        .find(|(key, _)| *key == text)
+        .map(|(_, value)| *value)
+        .unwrap_or(text)
+}
+
+/// 翻译带参数的文字，模板中的{}按顺序替换为args
+pub fn tf(template: &'static str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    t(template)
+        .split("{}")
+        .enumerate()
+        .fold(String::new(), |mut text, (index, part)| {
+            if index > 0 {
+                if let Some(arg) = args.next() {
+                    text.push_str(&arg.to_string());
+                }
+            }
+            text.push_str(part);
+            text
+        })
+}
+
+/// 按当前语言格式化数字，英文使用千位分隔符
+pub fn format_number(value: f64, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    if language() == Language::ZhCn {
+        return text;
+    }
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text.as_str()),
+    };
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, format!(".{}", fraction)),
+        None => (digits, String::new()),
+    };
+    let grouped = integer
+        .as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect::<Vec<&str>>()
+        .join(",");
+    format!("{}{}{}", sign, grouped, fraction)
+}
+
+/// 按当前语言缩写较大的数字，中文使用万和亿，英文使用K、M和B
+pub fn format_large(value: f64) -> String {
+    let units: &[(f64, &str)] = match language() {
+        Language::ZhCn => &[(1e8, "亿"), (1e4, "万")],
+        Language::EnUs => &[(1e9, "B"), (1e6, "M"), (1e3, "K")],
+    };
+    units
+        .iter()
+        .find(|(unit, _)| value.abs() >= *unit)
+        .map(|(unit, suffix)| format!("{}{}", format_number(value / unit, 2), suffix))
+        .unwrap_or_else(|| format_number(value, 0))
+}
+
+/// 将%Y-%m-%d %H:%M格式的时间按当前语言显示，英文为月/日/年
+pub fn format_datetime(datetime: &str) -> String {
+    match NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M") {
+        Ok(date_time) => match language() {
+            Language::ZhCn => datetime.to_string(),
+            Language::EnUs => date_time.format("%m/%d/%Y %H:%M").to_string(),
+        },
+        Err(_) => datetime.to_string(),
+    }
+}
+
+/// en-US的译文
+const EN_US: &[(&str, &str)] = &[
+    // 工具栏
+    ("指标", "Indicators"),
+    ("主题", "Theme"),
+    ("悬浮提示", "Tooltip"),
+    ("平均K线", "Heikin-Ashi"),
+    ("锚定VWAP", "Anchored VWAP"),
+    ("点击k线作为VWAP的起点", "Click a candle to anchor the VWAP"),
+    ("语言", "Language"),
+    // 图例和悬浮提示
+    ("日期", "Date"),
+    ("开盘", "Open"),
+    ("最高", "High"),
+    ("最低", "Low"),
+    ("收盘", "Close"),
+    ("涨跌", "Change"),
+    ("涨跌幅", "Change %"),
+    ("振幅", "Amplitude"),
+    ("数量", "Volume"),
+    ("成交额", "Turnover"),
+    ("持仓量", "Open interest"),
+    ("平均", "HA "),
+    ("原始: {} / {} / {} / {}", "Raw: {} / {} / {} / {}"),
+    ("显示悬浮提示", "Show tooltip"),
+    ("小数位数", "Decimals"),
+    ("开: {}", "O: {}"),
+    ("高: {}", "H: {}"),
+    ("低: {}", "L: {}"),
+    ("收: {}", "C: {}"),
+    ("涨跌: {}", "Chg: {}"),
+    ("涨跌幅: {}%", "Chg%: {}%"),
+    ("振幅: {}%", "Amp: {}%"),
+    ("量: {}", "Vol: {}"),
+    ("重置", "Reset"),
+    // 成交量图
+    ("成交量", "Volume"),
+    ("不显示持仓", "Hide OI"),
+    ("叠加持仓", "Overlay OI"),
+    ("持仓副图", "OI pane"),
+    // 指标
+    ("公式", "Formula"),
+    ("指标设置", "Indicator settings"),
+    ("删除", "Delete"),
+    ("撤销", "Undo"),
+    // 测量工具
+    ("{}根k线, {}", "{} bars, {}"),
+    ("{}天{}小时{}分钟", "{}d {}h {}m"),
+    ("{}小时{}分钟", "{}h {}m"),
+    ("{}分钟", "{}m"),
+    // 区间统计
+    ("区间统计", "Range statistics"),
+    ("所选区间没有k线", "No bars in the selected range"),
+    ("开始时间", "Start"),
+    ("结束时间", "End"),
+    ("k线数", "Bars"),
+    ("最大回撤", "Max drawdown"),
+    ("波动率", "Volatility"),
+    ("平均成交量", "Avg volume"),
+    ("上涨k线", "Up bars"),
+    ("下跌k线", "Down bars"),
+    ("复制", "Copy"),
+    ("取消选择", "Clear selection"),
+    ("重做", "Redo"),
+    ("添加", "Add"),
+    ("步长", "Step"),
+    ("上限", "Max"),
+    ("标准差带", "Std dev bands"),
+    ("副图", "Sub pane"),
+    ("周期", "Period"),
+    ("倍数", "Multiplier"),
+    ("线宽", "Width"),
+    // 图表样式
+    ("蜡烛图", "Candles"),
+    ("空心蜡烛图", "Hollow candles"),
+    ("美国线", "OHLC bars"),
+    ("折线图", "Line"),
+    ("面积图", "Area"),
+    ("时间K线", "Time bars"),
+    ("砖形图", "Renko"),
+    ("等幅K线", "Range bars"),
+    ("点数图", "Point & Figure"),
+    ("固定大小", "Fixed size"),
+    ("收盘价", "Close"),
+    ("最高最低价", "High/Low"),
+    ("砖块", "Brick"),
+    ("幅度", "Range"),
+    ("格值", "Box size"),
+    ("反转", "Reversal"),
+    // 配色和主题
+    ("红涨绿跌", "Red up"),
+    ("绿涨红跌", "Green up"),
+    ("自定义", "Custom"),
+    ("十字星", "Doji"),
+    ("上涨", "Up"),
+    ("下跌", "Down"),
+    ("实体", "Body"),
+    ("影线", "Wick"),
+    ("跟随系统", "System"),
+    ("浅色", "Light"),
+    ("深色", "Dark"),
+    ("背景", "Background"),
+    ("网格", "Grid"),
+    ("十字线", "Crosshair"),
+    ("文字", "Text"),
+    ("十字线宽度", "Crosshair width"),
+    ("蜡烛图线宽", "Candle line width"),
+    ("导出", "Export"),
+    ("导入", "Import"),
+    (
+        "粘贴主题JSON后点击导入",
+        "Paste theme JSON and click Import",
+    ),
+    // 画线
+    ("画线", "Draw"),
+    ("光标", "Cursor"),
+    ("趋势线", "Trend line"),
+    ("射线", "Ray"),
+    ("延长线", "Extended line"),
+    ("水平线", "Horizontal line"),
+    ("垂直线", "Vertical line"),
+    ("删除选中的画线", "Delete the selected drawing"),
+    ("斐波那契", "Fibonacci"),
+    ("斐波那契回撤", "Fib retracement"),
+    ("斐波那契扩展", "Fib extension"),
+    ("斐波那契时间周期", "Fib time zones"),
+    ("矩形", "Rectangle"),
+    ("椭圆", "Ellipse"),
+    ("平行通道", "Parallel channel"),
+    ("箭头", "Arrow"),
+    ("注释", "Note"),
+    ("属性", "Properties"),
+    (
+        "双击画线也可以打开属性",
+        "Double-click a drawing to open its properties",
+    ),
+    ("画线属性", "Drawing properties"),
+    ("新画线的样式", "Style for new drawings"),
+    ("颜色", "Color"),
+    ("线型", "Line style"),
+    ("实线", "Solid"),
+    ("虚线", "Dashed"),
+    ("点线", "Dotted"),
+    ("填充", "Fill"),
+    ("字号", "Font size"),
+    ("分享画线", "Share drawings"),
+    ("已复制到剪贴板", "Copied to clipboard"),
+    (
+        "粘贴画线JSON后点击导入",
+        "Paste drawings JSON and click Import",
+    ),
+    ("已导入{}条画线到{}", "Imported {} drawings into {}"),
+    ("合约代码", "Symbol"),
+    ("无磁吸", "Magnet off"),
+    ("弱磁吸", "Weak magnet"),
+    ("强磁吸", "Strong magnet"),
+    (
+        "十字线和画线锚点吸附到k线的开高低收",
+        "Snap the crosshair and drawing anchors to candle OHLC",
+    ),
+    // 错误信息
+    ("解析错误", "Failed to parse response"),
+    ("请求错误", "Request failed"),
+    ("日期格式错误: {}", "Invalid date: {}"),
+    ("公式错误: {}", "Formula error: {}"),
+    ("缺少分号", "missing semicolon"),
+    ("公式没有输出", "formula has no output"),
+    ("无效的数字{}", "invalid number {}"),
+    ("无法识别的字符'{}'", "unrecognized character '{}'"),
+    ("第{}个字符: {}", "at character {}: {}"),
+    ("公式结尾: {}", "at end of formula: {}"),
+    ("缺少表达式", "expected an expression"),
+    ("缺少右括号", "missing closing parenthesis"),
+    ("未定义的变量{}", "undefined variable {}"),
+    (
+        "{}是内置变量，不能重新定义",
+        "{} is a built-in variable and cannot be redefined",
+    ),
+    ("未知的函数", "unknown function"),
+    ("缺少逗号或右括号", "expected comma or closing parenthesis"),
+    ("{}需要{}个参数", "{} takes {} arguments"),
+    (
+        "{}的第2个参数必须是数字",
+        "the second argument of {} must be a number",
+    ),
+];
@@ -0,0 +1,1218 @@
+use egui::{Color32, ComboBox, Context, DragValue, Id, TextEdit, Ui, Window};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    colors::CandleColors,
+    formula::FormulaCache,
+    history::{ChartCommand, ListEdit},
+    i18n::t,
+    real_data::{Candle, Direction, RealData},
+    utils::DateTimeUtils,
+};
+
+/// 公式有多个输出时，第一个输出使用指标颜色，其余依次使用这些颜色
+const OUTPUT_COLORS: [Color32; 4] = [
+    Color32::from_rgb(30, 144, 255),
+    Color32::from_rgb(186, 85, 211),
+    Color32::from_rgb(46, 139, 87),
+    Color32::from_rgb(218, 165, 32),
+];
+
+/// 指标计算时取值的价格字段
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PriceSource {
+    Open,
+    High,
+    Low,
+    Close,
+    /// (最高 + 最低) / 2
+    Hl2,
+    /// (最高 + 最低 + 收盘) / 3
+    Hlc3,
+}
+
+impl PriceSource {
+    pub const ALL: [PriceSource; 6] = [
+        PriceSource::Open,
+        PriceSource::High,
+        PriceSource::Low,
+        PriceSource::Close,
+        PriceSource::Hl2,
+        PriceSource::Hlc3,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PriceSource::Open => "open",
+            PriceSource::High => "high",
+            PriceSource::Low => "low",
+            PriceSource::Close => "close",
+            PriceSource::Hl2 => "hl2",
+            PriceSource::Hlc3 => "hlc3",
+        }
+    }
+
+    /// 从一根k线中取出对应字段的值
+    pub fn value(&self, candle: &Candle) -> f64 {
+        match self {
+            PriceSource::Open => candle.open,
+            PriceSource::High => candle.high,
+            PriceSource::Low => candle.low,
+            PriceSource::Close => candle.close,
+            PriceSource::Hl2 => (candle.high + candle.low) / 2.0,
+            PriceSource::Hlc3 => (candle.high + candle.low + candle.close) / 3.0,
+        }
+    }
+}
+
+/// 指标类型
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum IndicatorKind {
+    /// 简单移动平均
+    Ma,
+    /// 指数移动平均
+    Ema,
+    /// 布林带
+    Boll,
+    /// 平均真实波幅，显示在副图
+    Atr,
+    /// 抛物线转向
+    Sar,
+    /// 超级趋势
+    Supertrend,
+    /// 按交易日重置的成交量加权平均价
+    Vwap,
+    /// 从指定k线开始累计的成交量加权平均价
+    AnchoredVwap,
+    /// 一目均衡表
+    Ichimoku,
+    /// 用户自定义公式
+    Formula,
+}
+
+impl IndicatorKind {
+    /// 设置窗口中可以直接添加的指标，锚定VWAP需要在k线图上点击添加
+    pub const ALL: [IndicatorKind; 9] = [
+        IndicatorKind::Ma,
+        IndicatorKind::Ema,
+        IndicatorKind::Boll,
+        IndicatorKind::Atr,
+        IndicatorKind::Sar,
+        IndicatorKind::Supertrend,
+        IndicatorKind::Vwap,
+        IndicatorKind::Ichimoku,
+        IndicatorKind::Formula,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IndicatorKind::Ma => "MA",
+            IndicatorKind::Ema => "EMA",
+            IndicatorKind::Boll => "BOLL",
+            IndicatorKind::Atr => "ATR",
+            IndicatorKind::Sar => "SAR",
+            IndicatorKind::Supertrend => "SuperTrend",
+            IndicatorKind::Vwap => "VWAP",
+            IndicatorKind::AnchoredVwap => "AVWAP",
+            IndicatorKind::Ichimoku => "Ichimoku",
+            IndicatorKind::Formula => t("公式"),
+        }
+    }
+
+    /// 新建指标时使用的默认周期
+    pub fn default_period(&self) -> usize {
+        match self {
+            IndicatorKind::Atr => 14,
+            IndicatorKind::Supertrend | IndicatorKind::Ichimoku => 9,
+            _ => 20,
+        }
+    }
+
+    /// 是否使用PriceSource取值，ATR等指标固定使用最高、最低、收盘价
+    pub fn uses_source(&self) -> bool {
+        matches!(
+            self,
+            IndicatorKind::Ma | IndicatorKind::Ema | IndicatorKind::Boll
+        )
+    }
+}
+
+/// 单个指标的参数
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct IndicatorConfig {
+    pub kind: IndicatorKind,
+    /// 计算周期
+    pub period: usize,
+    /// 倍数，BOLL和VWAP为标准差倍数，SuperTrend为ATR倍数
+    pub multiplier: f64,
+    /// VWAP是否显示标准差带
+    pub bands: bool,
+    /// 锚定VWAP的起始k线的日期时间
+    pub anchor: Option<String>,
+    /// 一目均衡表的基准线周期，同时也是先行带和延迟线的位移
+    pub kijun_period: usize,
+    /// 一目均衡表的先行带B周期
+    pub senkou_period: usize,
+    /// 自定义公式的源码
+    pub formula: String,
+    /// 自定义公式是否显示在副图中
+    pub sub_pane: bool,
+    /// SAR的加速因子步长
+    pub step: f64,
+    /// SAR的加速因子上限
+    pub max_step: f64,
+    pub source: PriceSource,
+    pub color: Color32,
+    pub width: f32,
+    /// 自定义公式的解析结果
+    #[serde(skip)]
+    formula_cache: FormulaCache,
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        Self::new(IndicatorKind::Ma, 5, Color32::from_rgb(255, 165, 0))
+    }
+}
+
+impl IndicatorConfig {
+    pub fn new(kind: IndicatorKind, period: usize, color: Color32) -> Self {
+        Self {
+            kind,
+            period,
+            multiplier: match kind {
+                IndicatorKind::Supertrend => 3.0,
+                IndicatorKind::Vwap | IndicatorKind::AnchoredVwap => 1.0,
+                _ => 2.0,
+            },
+            bands: false,
+            anchor: None,
+            kijun_period: 26,
+            senkou_period: 52,
+            formula: "DIF:EMA(C,12)-EMA(C,26);DEA:EMA(DIF,9);".to_string(),
+            sub_pane: true,
+            step: 0.02,
+            max_step: 0.2,
+            source: PriceSource::Close,
+            color,
+            width: 1.0,
+            formula_cache: Default::default(),
+        }
+    }
+
+    /// 公式源码修改后重新解析，其他指标不需要解析
+    fn update_formula(&mut self) {
+        if self.kind == IndicatorKind::Formula {
+            self.formula_cache.update(&self.formula);
+        }
+    }
+
+    /// 是否显示在单独的副图中
+    pub fn is_sub_pane(&self) -> bool {
+        match self.kind {
+            IndicatorKind::Atr => true,
+            IndicatorKind::Formula => self.sub_pane,
+            _ => false,
+        }
+    }
+
+    /// 指标的显示名称，例如MA(5)
+    pub fn label(&self) -> String {
+        match self.kind {
+            IndicatorKind::Boll | IndicatorKind::Supertrend => {
+                format!("{}({},{})", self.kind.name(), self.period, self.multiplier)
+            }
+            IndicatorKind::Sar => format!("{}({},{})", self.kind.name(), self.step, self.max_step),
+            IndicatorKind::Vwap => self.kind.name().to_string(),
+            IndicatorKind::Ichimoku => format!(
+                "{}({},{},{})",
+                self.kind.name(),
+                self.period,
+                self.kijun_period,
+                self.senkou_period
+            ),
+            IndicatorKind::AnchoredVwap => format!(
+                "{}({})",
+                self.kind.name(),
+                self.anchor
+                    .as_ref()
+                    .and_then(|anchor| DateTimeUtils::format_datetime_string(anchor))
+                    .unwrap_or_default()
+            ),
+            _ => format!("{}({})", self.kind.name(), self.period),
+        }
+    }
+
+    /// 计算指标的折线，x轴坐标与蜡烛图一致，colors用于区分涨跌的折线
+    pub fn lines(&self, real_datas: &[RealData], colors: &CandleColors) -> Vec<IndicatorLine> {
+        let values = real_datas
+            .iter()
+            .map(|real_data| self.source.value(&real_data.candle))
+            .collect::<Vec<f64>>();
+        match self.kind {
+            IndicatorKind::Ma => {
+                vec![self.line(self.label(), real_datas, &sma(&values, self.period))]
+            }
+            IndicatorKind::Ema => {
+                vec![self.line(self.label(), real_datas, &ema(&values, self.period))]
+            }
+            IndicatorKind::Boll => {
+                let middle = sma(&values, self.period);
+                let deviation = std_dev(&values, self.period);
+                let band = |sign: f64| {
+                    middle
+                        .iter()
+                        .zip(deviation.iter())
+                        .map(|(m, d)| match (m, d) {
+                            (Some(m), Some(d)) => Some(m + sign * self.multiplier * d),
+                            _ => None,
+                        })
+                        .collect::<Vec<Option<f64>>>()
+                };
+                vec![
+                    self.line("UP".to_string(), real_datas, &band(1.0)),
+                    self.line("MID".to_string(), real_datas, &middle),
+                    self.line("DN".to_string(), real_datas, &band(-1.0)),
+                ]
+            }
+            IndicatorKind::Atr => {
+                let candles = Self::candles(real_datas);
+                vec![self.line(self.label(), real_datas, &atr(&candles, self.period))]
+            }
+            IndicatorKind::Sar => {
+                let candles = Self::candles(real_datas);
+                let mut line = self.line(
+                    self.label(),
+                    real_datas,
+                    &parabolic_sar(&candles, self.step, self.max_step),
+                );
+                line.style = IndicatorStyle::Dots;
+                vec![line]
+            }
+            IndicatorKind::Supertrend => {
+                let candles = Self::candles(real_datas);
+                self.supertrend_lines(
+                    real_datas,
+                    &supertrend(&candles, self.period, self.multiplier),
+                    colors,
+                )
+            }
+            IndicatorKind::Vwap => {
+                let candles = Self::candles(real_datas);
+                let trading_days = candles
+                    .iter()
+                    .map(|candle| DateTimeUtils::trading_day(&candle.datetime))
+                    .collect::<Vec<_>>();
+                let starts = trading_days
+                    .iter()
+                    .enumerate()
+                    .map(|(i, day)| i == 0 || trading_days[i - 1] != *day)
+                    .collect::<Vec<bool>>();
+                self.vwap_lines(real_datas, &vwap(&candles, &starts))
+            }
+            IndicatorKind::AnchoredVwap => {
+                let candles = Self::candles(real_datas);
+                let starts = candles
+                    .iter()
+                    .map(|candle| Some(&candle.datetime) == self.anchor.as_ref())
+                    .collect::<Vec<bool>>();
+                self.vwap_lines(real_datas, &vwap(&candles, &starts))
+            }
+            IndicatorKind::Ichimoku => self.ichimoku_lines(real_datas),
+            IndicatorKind::Formula => self.formula_lines(real_datas),
+        }
+    }
+
+    /// 自定义公式的输出，公式有错误或还没有解析时没有输出
+    ///
+    /// 条件输出作为信号，在条件成立的k线上画点，主图中画在最低价处，副图中画在1处
+    fn formula_lines(&self, real_datas: &[RealData]) -> Vec<IndicatorLine> {
+        let Some(Ok(formula)) = self.formula_cache.get() else {
+            return vec![];
+        };
+        formula
+            .evaluate(&Self::candles(real_datas))
+            .iter()
+            .enumerate()
+            .map(|(index, output)| {
+                let color = match index {
+                    0 => self.color,
+                    _ => OUTPUT_COLORS[(index - 1) % OUTPUT_COLORS.len()],
+                };
+                if output.is_signal {
+                    let values = real_datas
+                        .iter()
+                        .zip(output.values.iter())
+                        .map(|(real_data, value)| match value {
+                            Some(value) if *value != 0.0 => Some(if self.sub_pane {
+                                1.0
+                            } else {
+                                real_data.candle.low
+                            }),
+                            _ => None,
+                        })
+                        .collect::<Vec<Option<f64>>>();
+                    let mut line = self.line(output.name.to_owned(), real_datas, &values);
+                    line.color = color;
+                    line.style = IndicatorStyle::Dots;
+                    line
+                } else {
+                    let mut line = self.line(output.name.to_owned(), real_datas, &output.values);
+                    line.color = color;
+                    line
+                }
+            })
+            .collect()
+    }
+
+    /// 一目均衡表：转换线、基准线、向后平移的延迟线，以及向前平移到最后一根k线之后的先行带和云
+    fn ichimoku_lines(&self, real_datas: &[RealData]) -> Vec<IndicatorLine> {
+        let candles = Self::candles(real_datas);
+        let tenkan = midpoint(&candles, self.period);
+        let kijun = midpoint(&candles, self.kijun_period);
+        let senkou_a = tenkan
+            .iter()
+            .zip(kijun.iter())
+            .map(|(tenkan, kijun)| Some((tenkan.as_ref()? + kijun.as_ref()?) / 2.0))
+            .collect::<Vec<Option<f64>>>();
+        let senkou_b = midpoint(&candles, self.senkou_period);
+        let chikou = candles
+            .iter()
+            .map(|candle| Some(candle.close))
+            .collect::<Vec<Option<f64>>>();
+        let shift = self.kijun_period as f64;
+        let mut lines = vec![
+            self.shifted_line("Tenkan", real_datas, &tenkan, 0.0, self.color),
+            self.shifted_line(
+                "Kijun",
+                real_datas,
+                &kijun,
+                0.0,
+                Color32::from_rgb(30, 144, 255),
+            ),
+            self.shifted_line(
+                "Chikou",
+                real_datas,
+                &chikou,
+                -shift,
+                Color32::from_rgb(128, 128, 0),
+            ),
+        ];
+        let line_a = self.shifted_line(
+            "SenkouA",
+            real_datas,
+            &senkou_a,
+            shift,
+            Color32::from_rgb(46, 139, 87),
+        );
+        let line_b = self.shifted_line(
+            "SenkouB",
+            real_datas,
+            &senkou_b,
+            shift,
+            Color32::from_rgb(205, 92, 92),
+        );
+        lines.append(&mut cloud(&line_a, &line_b));
+        lines.push(line_a);
+        lines.push(line_b);
+        lines
+    }
+
+    /// x坐标平移shift个k线的折线
+    fn shifted_line(
+        &self,
+        name: &str,
+        real_datas: &[RealData],
+        values: &[Option<f64>],
+        shift: f64,
+        color: Color32,
+    ) -> IndicatorLine {
+        let mut line = self.line(name.to_string(), real_datas, values);
+        line.points.iter_mut().for_each(|point| point[0] += shift);
+        line.color = color;
+        line
+    }
+
+    /// VWAP折线以及可选的标准差带
+    fn vwap_lines(
+        &self,
+        real_datas: &[RealData],
+        values: &[Option<(f64, f64)>],
+    ) -> Vec<IndicatorLine> {
+        let band = |sign: f64| {
+            values
+                .iter()
+                .map(|value| {
+                    value.map(|(mean, deviation)| mean + sign * self.multiplier * deviation)
+                })
+                .collect::<Vec<Option<f64>>>()
+        };
+        let mut lines = vec![self.line(self.label(), real_datas, &band(0.0))];
+        if self.bands {
+            lines.push(self.line("UP".to_string(), real_datas, &band(1.0)));
+            lines.push(self.line("DN".to_string(), real_datas, &band(-1.0)));
+        }
+        lines
+    }
+
+    fn candles(real_datas: &[RealData]) -> Vec<Candle> {
+        real_datas
+            .iter()
+            .map(|real_data| real_data.candle.to_owned())
+            .collect()
+    }
+
+    fn line(&self, name: String, real_datas: &[RealData], values: &[Option<f64>]) -> IndicatorLine {
+        IndicatorLine {
+            name,
+            points: real_datas
+                .iter()
+                .zip(values.iter())
+                .filter_map(|(real_data, value)| value.map(|v| [real_data.box_elem.argument, v]))
+                .collect(),
+            color: self.color,
+            width: self.width,
+            style: IndicatorStyle::Line,
+        }
+    }
+
+    /// SuperTrend在趋势反转时换边，每段连续的趋势单独成为一条折线，按涨跌配色着色
+    fn supertrend_lines(
+        &self,
+        real_datas: &[RealData],
+        values: &[Option<(f64, bool)>],
+        colors: &CandleColors,
+    ) -> Vec<IndicatorLine> {
+        let mut lines: Vec<IndicatorLine> = vec![];
+        let mut prev_up: Option<bool> = None;
+        real_datas
+            .iter()
+            .zip(values.iter())
+            .for_each(|(real_data, value)| match value {
+                Some((value, up)) => {
+                    let point = [real_data.box_elem.argument, *value];
+                    match lines.last_mut() {
+                        Some(line) if prev_up == Some(*up) => line.points.push(point),
+                        _ => lines.push(IndicatorLine {
+                            name: self.label(),
+                            points: vec![point],
+                            color: colors.body(if *up { Direction::Up } else { Direction::Down }),
+                            width: self.width,
+                            style: IndicatorStyle::Line,
+                        }),
+                    }
+                    prev_up = Some(*up);
+                }
+                None => prev_up = None,
+            });
+        lines
+    }
+}
+
+/// 指标的绘制方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndicatorStyle {
+    /// 折线
+    Line,
+    /// 圆点，例如SAR
+    Dots,
+    /// 填充区域，points是多边形的顶点，例如一目均衡表的云
+    Area,
+}
+
+/// 计算完成、可以直接绘制的指标折线
+#[derive(Debug, Clone)]
+pub struct IndicatorLine {
+    pub name: String,
+    pub points: Vec<[f64; 2]>,
+    pub color: Color32,
+    pub width: f32,
+    pub style: IndicatorStyle,
+}
+
+impl IndicatorLine {
+    /// x坐标处的指标值，填充区域没有值
+    pub fn value_at(&self, x: f64) -> Option<f64> {
+        if self.style == IndicatorStyle::Area {
+            return None;
+        }
+        self.points
+            .iter()
+            .find(|point| point[0] == x)
+            .map(|point| point[1])
+    }
+}
+
+/// 一目均衡表的云，把先行带A和B之间的区域按k线切分成多边形，A在上方时为绿色，B在上方时为红色
+fn cloud(line_a: &IndicatorLine, line_b: &IndicatorLine) -> Vec<IndicatorLine> {
+    let area = |points: Vec<[f64; 2]>, rising: bool| IndicatorLine {
+        name: "Kumo".to_string(),
+        points,
+        color: if rising {
+            Color32::from_rgb(46, 139, 87)
+        } else {
+            Color32::from_rgb(205, 92, 92)
+        },
+        width: 0.0,
+        style: IndicatorStyle::Area,
+    };
+    paired_points(line_a, line_b)
+        .windows(2)
+        .flat_map(|window| {
+            let ((x0, a0, b0), (x1, a1, b1)) = (window[0], window[1]);
+            if (a0 - b0) * (a1 - b1) >= 0.0 {
+                vec![area(
+                    vec![[x0, a0], [x1, a1], [x1, b1], [x0, b0]],
+                    a0 + a1 >= b0 + b1,
+                )]
+            } else {
+                // 两条线在这一段内交叉，在交点处分成两个三角形
+                let t = (a0 - b0) / ((a0 - b0) - (a1 - b1));
+                let cross = [x0 + t * (x1 - x0), a0 + t * (a1 - a0)];
+                vec![
+                    area(vec![[x0, a0], cross, [x0, b0]], a0 > b0),
+                    area(vec![cross, [x1, a1], [x1, b1]], a1 > b1),
+                ]
+            }
+        })
+        .collect()
+}
+
+/// 按x坐标一次合并两条线的点，得到两条线都有值的(x, a, b)
+///
+/// 两条线的点都按x递增，x为k线的位置，相差不到半根k线即为同一根
+fn paired_points(line_a: &IndicatorLine, line_b: &IndicatorLine) -> Vec<(f64, f64, f64)> {
+    let mut pairs = vec![];
+    let (mut points_a, mut points_b) = (
+        line_a.points.iter().peekable(),
+        line_b.points.iter().peekable(),
+    );
+    while let (Some(a), Some(b)) = (points_a.peek(), points_b.peek()) {
+        if (a[0] - b[0]).abs() < 0.5 {
+            pairs.push((a[0], a[1], b[1]));
+            points_a.next();
+            points_b.next();
+        } else if a[0] < b[0] {
+            points_a.next();
+        } else {
+            points_b.next();
+        }
+    }
+    pairs
+}
+
+/// 周期内最高价和最低价的中点
+pub fn midpoint(candles: &[Candle], period: usize) -> Vec<Option<f64>> {
+    let period = period.max(1);
+    candles
+        .iter()
+        .enumerate()
+        .map(|(i, _)| {
+            if i + 1 < period {
+                return None;
+            }
+            let window = &candles[i + 1 - period..=i];
+            let high = window
+                .iter()
+                .fold(f64::NEG_INFINITY, |acc, c| acc.max(c.high));
+            let low = window.iter().fold(f64::INFINITY, |acc, c| acc.min(c.low));
+            Some((high + low) / 2.0)
+        })
+        .collect()
+}
+
+/// 简单移动平均，数据不足一个周期的位置为None
+pub fn sma(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let period = period.max(1);
+    let mut sum = 0.0;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            sum += value;
+            if i >= period {
+                sum -= values[i - period];
+            }
+            if i + 1 >= period {
+                Some(sum / period as f64)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// 指数移动平均，以第一个周期的简单平均作为初始值
+pub fn ema(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let period = period.max(1);
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut prev: Option<f64> = None;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            if i + 1 < period {
+                return None;
+            }
+            let next = match prev {
+                Some(prev) => prev + alpha * (value - prev),
+                None => values[i + 1 - period..=i].iter().sum::<f64>() / period as f64,
+            };
+            prev = Some(next);
+            prev
+        })
+        .collect()
+}
+
+/// 真实波幅，第一根k线没有前收盘价，使用最高价-最低价
+fn true_range(candles: &[Candle]) -> Vec<f64> {
+    candles
+        .iter()
+        .enumerate()
+        .map(|(i, candle)| {
+            let range = candle.high - candle.low;
+            match i.checked_sub(1).map(|prev| candles[prev].close) {
+                Some(prev_close) => range
+                    .max((candle.high - prev_close).abs())
+                    .max((candle.low - prev_close).abs()),
+                None => range,
+            }
+        })
+        .collect()
+}
+
+/// 平均真实波幅，使用Wilder平滑，以第一个周期的简单平均作为初始值
+pub fn atr(candles: &[Candle], period: usize) -> Vec<Option<f64>> {
+    let period = period.max(1);
+    let true_ranges = true_range(candles);
+    let mut prev: Option<f64> = None;
+    true_ranges
+        .iter()
+        .enumerate()
+        .map(|(i, true_range)| {
+            if i + 1 < period {
+                return None;
+            }
+            let next = match prev {
+                Some(prev) => (prev * (period - 1) as f64 + true_range) / period as f64,
+                None => true_ranges[..=i].iter().sum::<f64>() / period as f64,
+            };
+            prev = Some(next);
+            prev
+        })
+        .collect()
+}
+
+/// 抛物线转向，step为加速因子步长，max_step为加速因子上限
+///
+/// 以前两根k线的收盘价判断初始趋势，第一根k线没有值
+pub fn parabolic_sar(candles: &[Candle], step: f64, max_step: f64) -> Vec<Option<f64>> {
+    if candles.len() < 2 {
+        return vec![None; candles.len()];
+    }
+    let mut up = candles[1].close >= candles[0].close;
+    let mut sar = if up { candles[0].low } else { candles[0].high };
+    let mut extreme = if up { candles[0].high } else { candles[0].low };
+    let mut factor = step;
+    let mut result = vec![None];
+    for i in 1..candles.len() {
+        let candle = &candles[i];
+        sar += factor * (extreme - sar);
+        // SAR不能进入前两根k线的价格区间
+        let prev_candles = &candles[i.saturating_sub(2)..i];
+        if up {
+            sar = prev_candles.iter().fold(sar, |sar, prev| sar.min(prev.low));
+            if candle.low < sar {
+                up = false;
+                sar = extreme;
+                extreme = candle.low;
+                factor = step;
+            } else if candle.high > extreme {
+                extreme = candle.high;
+                factor = (factor + step).min(max_step);
+            }
+        } else {
+            sar = prev_candles
+                .iter()
+                .fold(sar, |sar, prev| sar.max(prev.high));
+            if candle.high > sar {
+                up = true;
+                sar = extreme;
+                extreme = candle.high;
+                factor = step;
+            } else if candle.low < extreme {
+                extreme = candle.low;
+                factor = (factor + step).min(max_step);
+            }
+        }
+        result.push(Some(sar));
+    }
+    result
+}
+
+/// 超级趋势，返回每根k线的趋势线值和是否为上涨趋势
+pub fn supertrend(candles: &[Candle], period: usize, multiplier: f64) -> Vec<Option<(f64, bool)>> {
+    let atrs = atr(candles, period);
+    let mut prev: Option<(f64, f64, bool)> = None;
+    candles
+        .iter()
+        .enumerate()
+        .map(|(i, candle)| {
+            let atr = atrs[i]?;
+            let middle = (candle.high + candle.low) / 2.0;
+            let (mut upper, mut lower) = (middle + multiplier * atr, middle - multiplier * atr);
+            let mut up = true;
+            if let Some((prev_upper, prev_lower, prev_up)) = prev {
+                let prev_close = candles[i - 1].close;
+                // 上轨只能下移，下轨只能上移，除非前收盘价突破
+                if upper > prev_upper && prev_close <= prev_upper {
+                    upper = prev_upper;
+                }
+                if lower < prev_lower && prev_close >= prev_lower {
+                    lower = prev_lower;
+                }
+                up = if prev_up {
+                    candle.close >= lower
+                } else {
+                    candle.close > upper
+                };
+            }
+            prev = Some((upper, lower, up));
+            Some((if up { lower } else { upper }, up))
+        })
+        .collect()
+}
+
+/// 成交量加权平均价，使用(最高+最低+收盘)/3作为成交价
+///
+/// starts为true的位置重新开始累计，第一个起点之前没有值。返回VWAP和成交量加权的标准差。
+pub fn vwap(candles: &[Candle], starts: &[bool]) -> Vec<Option<(f64, f64)>> {
+    let (mut volume, mut amount, mut square) = (0.0, 0.0, 0.0);
+    let mut started = false;
+    candles
+        .iter()
+        .zip(starts.iter())
+        .map(|(candle, start)| {
+            if *start {
+                started = true;
+                (volume, amount, square) = (0.0, 0.0, 0.0);
+            }
+            if !started {
+                return None;
+            }
+            let price = PriceSource::Hlc3.value(candle);
+            volume += candle.volume;
+            amount += price * candle.volume;
+            square += price * price * candle.volume;
+            if volume <= 0.0 {
+                return None;
+            }
+            let mean = amount / volume;
+            Some((mean, (square / volume - mean * mean).max(0.0).sqrt()))
+        })
+        .collect()
+}
+
+/// 周期内的总体标准差
+pub fn std_dev(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let period = period.max(1);
+    sma(values, period)
+        .iter()
+        .enumerate()
+        .map(|(i, mean)| {
+            mean.map(|mean| {
+                let variance = values[i + 1 - period..=i]
+                    .iter()
+                    .map(|value| (value - mean).powi(2))
+                    .sum::<f64>()
+                    / period as f64;
+                variance.sqrt()
+            })
+        })
+        .collect()
+}
+
+/// 指标设置，会随App一起存储
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct IndicatorSettings {
+    pub indicators: Vec<IndicatorConfig>,
+    /// 设置窗口是否打开
+    #[serde(skip)]
+    pub open: bool,
+    /// 设置窗口中准备添加的指标类型
+    #[serde(skip)]
+    new_kind: IndicatorKind,
+    /// 还没有记录到撤销历史的修改
+    #[serde(skip)]
+    commands: Vec<ChartCommand>,
+}
+
+impl Default for IndicatorSettings {
+    fn default() -> Self {
+        Self {
+            indicators: vec![
+                IndicatorConfig::new(IndicatorKind::Ma, 5, Color32::from_rgb(255, 165, 0)),
+                IndicatorConfig::new(IndicatorKind::Ma, 10, Color32::from_rgb(30, 144, 255)),
+                IndicatorConfig::new(IndicatorKind::Ma, 20, Color32::from_rgb(186, 85, 211)),
+            ],
+            open: false,
+            new_kind: IndicatorKind::Ma,
+            commands: vec![],
+        }
+    }
+}
+
+impl IndicatorSettings {
+    /// 添加一个从datetime开始的锚定VWAP
+    pub fn add_anchored_vwap(&mut self, datetime: String) {
+        let mut indicator = IndicatorConfig::new(
+            IndicatorKind::AnchoredVwap,
+            1,
+            Color32::from_rgb(0, 139, 139),
+        );
+        indicator.anchor = Some(datetime);
+        self.add(indicator);
+    }
+
+    fn add(&mut self, indicator: IndicatorConfig) {
+        self.record(ListEdit::Add(self.indicators.len(), indicator.to_owned()));
+        self.indicators.push(indicator);
+    }
+
+    fn record(&mut self, edit: ListEdit<IndicatorConfig>) {
+        self.commands.push(ChartCommand::Indicator(Box::new(edit)));
+    }
+
+    /// 取出这一帧的修改，由图表记录到撤销历史
+    pub fn take_commands(&mut self) -> Vec<ChartCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+    /// 解析修改过的公式，在计算折线之前调用
+    pub fn update_formulas(&mut self) {
+        self.indicators
+            .iter_mut()
+            .for_each(IndicatorConfig::update_formula);
+    }
+
+    /// 计算所有主图指标的折线
+    pub fn lines(&self, real_datas: &[RealData], colors: &CandleColors) -> Vec<IndicatorLine> {
+        self.indicators
+            .iter()
+            .filter(|indicator| !indicator.is_sub_pane())
+            .flat_map(|indicator| indicator.lines(real_datas, colors))
+            .collect()
+    }
+
+    /// 计算所有副图指标的折线，每个指标一个副图，返回副图的id和折线
+    pub fn sub_panes(
+        &self,
+        real_datas: &[RealData],
+        colors: &CandleColors,
+    ) -> Vec<(String, Vec<IndicatorLine>)> {
+        self.indicators
+            .iter()
+            .enumerate()
+            .filter(|(_, indicator)| indicator.is_sub_pane())
+            .map(|(index, indicator)| {
+                (
+                    format!("kline_indicator_{}", index),
+                    indicator.lines(real_datas, colors),
+                )
+            })
+            .collect()
+    }
+
+    /// 显示指标设置窗口
+    pub fn show_window(&mut self, ctx: &Context) {
+        let mut open = self.open;
+        Window::new(t("指标设置"))
+            .id(Id::new("indicator_settings"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let mut removed = None;
+                for (index, indicator) in self.indicators.iter_mut().enumerate() {
+                    let before = indicator.to_owned();
+                    ui.horizontal(|ui| {
+                        ui.label(indicator.kind.name());
+                        if Self::indicator_row(ui, index, indicator) {
+                            self.commands.push(ChartCommand::Indicator(Box::new(
+                                ListEdit::Update(index, before, indicator.to_owned()),
+                            )));
+                        }
+                        if ui.small_button(t("删除")).clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                    indicator.update_formula();
+                    if let Some(Err(err)) = indicator.formula_cache.get() {
+                        ui.colored_label(Color32::RED, err.to_string());
+                    }
+                }
+                if let Some(index) = removed {
+                    let indicator = self.indicators.remove(index);
+                    self.record(ListEdit::Remove(index, indicator));
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ComboBox::from_id_source("new_indicator_kind")
+                        .selected_text(self.new_kind.name())
+                        .show_ui(ui, |ui| {
+                            IndicatorKind::ALL.iter().for_each(|kind| {
+                                ui.selectable_value(&mut self.new_kind, *kind, kind.name());
+                            });
+                        });
+                    if ui.button(t("添加")).clicked() {
+                        self.add(IndicatorConfig::new(
+                            self.new_kind,
+                            self.new_kind.default_period(),
+                            Color32::from_rgb(255, 165, 0),
+                        ));
+                    }
+                });
+            });
+        self.open = open;
+    }
+
+    /// 单个指标的参数编辑，返回参数是否被修改
+    fn indicator_row(ui: &mut Ui, index: usize, indicator: &mut IndicatorConfig) -> bool {
+        let mut changed = false;
+        match indicator.kind {
+            IndicatorKind::Sar => {
+                ui.label(t("步长"));
+                changed |= ui
+                    .add(
+                        DragValue::new(&mut indicator.step)
+                            .clamp_range(0.001..=1.0)
+                            .speed(0.001),
+                    )
+                    .changed();
+                ui.label(t("上限"));
+                changed |= ui
+                    .add(
+                        DragValue::new(&mut indicator.max_step)
+                            .clamp_range(0.01..=1.0)
+                            .speed(0.01),
+                    )
+                    .changed();
+            }
+            IndicatorKind::Vwap | IndicatorKind::AnchoredVwap => {
+                if let Some(anchor) = indicator
+                    .anchor
+                    .as_deref()
+                    .and_then(DateTimeUtils::format_datetime_string)
+                {
+                    ui.label(anchor);
+                }
+                changed |= ui.checkbox(&mut indicator.bands, t("标准差带")).changed();
+            }
+            IndicatorKind::Formula => {
+                changed |= ui
+                    .add(
+                        TextEdit::multiline(&mut indicator.formula)
+                            .code_editor()
+                            .desired_rows(2)
+                            .desired_width(240.0),
+                    )
+                    .changed();
+                changed |= ui.checkbox(&mut indicator.sub_pane, t("副图")).changed();
+            }
+            IndicatorKind::Ichimoku => {
+                ui.label(t("周期"));
+                [
+                    &mut indicator.period,
+                    &mut indicator.kijun_period,
+                    &mut indicator.senkou_period,
+                ]
+                .into_iter()
+                .for_each(|period| {
+                    changed |= ui
+                        .add(DragValue::new(period).clamp_range(1..=500))
+                        .changed();
+                });
+            }
+            _ => {
+                ui.label(t("周期"));
+                changed |= ui
+                    .add(DragValue::new(&mut indicator.period).clamp_range(1..=500))
+                    .changed();
+            }
+        }
+        if matches!(
+            indicator.kind,
+            IndicatorKind::Boll | IndicatorKind::Supertrend
+        ) || indicator.bands
+        {
+            ui.label(t("倍数"));
+            changed |= ui
+                .add(
+                    DragValue::new(&mut indicator.multiplier)
+                        .clamp_range(0.1..=10.0)
+                        .speed(0.1),
+                )
+                .changed();
+        }
+        if indicator.kind.uses_source() {
+            ComboBox::from_id_source(("indicator_source", index))
+                .width(60.0)
+                .selected_text(indicator.source.name())
+                .show_ui(ui, |ui| {
+                    PriceSource::ALL.iter().for_each(|source| {
+                        changed |= ui
+                            .selectable_value(&mut indicator.source, *source, source.name())
+                            .changed();
+                    });
+                });
+        }
+        changed |= ui.color_edit_button_srgba(&mut indicator.color).changed();
+        ui.label(t("线宽"));
+        changed |= ui
+            .add(
+                DragValue::new(&mut indicator.width)
+                    .clamp_range(0.5..=5.0)
+                    .speed(0.1),
+            )
+            .changed();
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 参考数据：(开盘, 最高, 最低, 收盘)
+    const REFERENCE: [(f64, f64, f64, f64); 12] = [
+        (10.0, 10.5, 9.8, 10.2),
+        (10.2, 10.8, 10.1, 10.7),
+        (10.7, 11.0, 10.4, 10.5),
+        (10.5, 10.6, 10.0, 10.1),
+        (10.1, 10.3, 9.7, 9.8),
+        (9.8, 10.0, 9.5, 9.9),
+        (9.9, 10.4, 9.9, 10.3),
+        (10.3, 10.9, 10.2, 10.8),
+        (10.8, 11.2, 10.7, 11.1),
+        (11.1, 11.3, 10.6, 10.7),
+        (10.7, 10.8, 10.2, 10.3),
+        (10.3, 10.5, 9.9, 10.0),
+    ];
+
+    fn candles() -> Vec<Candle> {
+        REFERENCE
+            .iter()
+            .enumerate()
+            .map(|(index, (open, high, low, close))| {
+                Candle::for_test(index as i64, *open, *high, *low, *close, 100.0)
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: &[Option<f64>], expected: &[Option<f64>]) {
+        assert_eq!(actual.len(), expected.len());
+        actual
+            .iter()
+            .zip(expected.iter())
+            .enumerate()
+            .for_each(|(i, (actual, expected))| match (actual, expected) {
+                (Some(actual), Some(expected)) => assert!(
+                    (actual - expected).abs() < 1e-6,
+                    "index {}: {} != {}",
+                    i,
+                    actual,
+                    expected
+                ),
+                (None, None) => {}
+                _ => panic!("index {}: {:?} != {:?}", i, actual, expected),
+            });
+    }
+
+    #[test]
+    fn atr_matches_reference() {
+        assert_close(
+            &atr(&candles(), 3),
+            &[
+                None,
+                None,
+                Some(0.666667),
+                Some(0.644444),
+                Some(0.629630),
+                Some(0.586420),
+                Some(0.557613),
+                Some(0.605075),
+                Some(0.570050),
+                Some(0.613367),
+                Some(0.608911),
+                Some(0.605941),
+            ],
+        );
+    }
+
+    #[test]
+    fn parabolic_sar_matches_reference() {
+        assert_close(
+            &parabolic_sar(&candles(), 0.02, 0.2),
+            &[
+                None,
+                Some(9.8),
+                Some(9.8),
+                Some(9.872),
+                Some(11.0),
+                Some(10.974),
+                Some(10.91504),
+                Some(9.5),
+                Some(9.528),
+                Some(9.59488),
+                Some(9.697187),
+                Some(9.793356),
+            ],
+        );
+    }
+
+    #[test]
+    fn supertrend_matches_reference() {
+        let result = supertrend(&candles(), 3, 1.0);
+        assert_close(
+            &result
+                .iter()
+                .map(|value| value.map(|(value, _)| value))
+                .collect::<Vec<Option<f64>>>(),
+            &[
+                None,
+                None,
+                Some(10.033333),
+                Some(10.033333),
+                Some(10.629630),
+                Some(10.336420),
+                Some(10.336420),
+                Some(9.944925),
+                Some(10.379950),
+                Some(10.379950),
+                Some(11.108911),
+                Some(10.805941),
+            ],
+        );
+        assert_eq!(
+            result
+                .iter()
+                .map(|value| value.map(|(_, up)| up))
+                .collect::<Vec<Option<bool>>>(),
+            vec![
+                None,
+                None,
+                Some(true),
+                Some(true),
+                Some(false),
+                Some(false),
+                Some(false),
+                Some(true),
+                Some(true),
+                Some(true),
+                Some(false),
+                Some(false),
+            ]
+        );
+    }
+}
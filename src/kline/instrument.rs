@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+use super::{i18n::format_number, real_data::Candle};
+
+/// 合约信息，由数据源随k线一起返回，没有时使用默认值
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Instrument {
+    /// 最小变动价位
+    pub tick_size: f64,
+    /// 价格显示的小数位数
+    pub price_decimals: usize,
+    /// 成交量的单位
+    pub volume_unit: String,
+    /// 合约乘数，用于估算成交额
+    pub multiplier: f64,
+}
+
+impl Default for Instrument {
+    fn default() -> Self {
+        Self {
+            tick_size: 0.01,
+            price_decimals: 2,
+            volume_unit: String::new(),
+            multiplier: 1.0,
+        }
+    }
+}
+
+impl Instrument {
+    /// 将价格对齐到最小变动价位
+    pub fn round_price(&self, price: f64) -> f64 {
+        if self.tick_size > 0.0 {
+            (price / self.tick_size).round() * self.tick_size
+        } else {
+            price
+        }
+    }
+
+    /// 按最小变动价位和小数位数格式化价格，避免出现8123.000000001
+    pub fn format_price(&self, price: f64) -> String {
+        format_number(self.round_price(price), self.price_decimals)
+    }
+
+    /// 带符号的价格变动
+    pub fn format_change(&self, change: f64) -> String {
+        let change = self.round_price(change);
+        let sign = if change > 0.0 { "+" } else { "" };
+        format!("{}{}", sign, format_number(change, self.price_decimals))
+    }
+
+    /// 带单位的成交量
+    pub fn format_volume(&self, volume: f64) -> String {
+        format!("{}{}", format_number(volume, 0), self.volume_unit)
+    }
+
+    /// 成交额，数据源没有提供时使用收盘价*成交量*合约乘数估算
+    pub fn turnover(&self, candle: &Candle) -> f64 {
+        candle
+            .turnover
+            .unwrap_or(candle.close * candle.volume * self.multiplier)
+    }
+}
@@ -0,0 +1,70 @@
+use egui::plot::{PlotPoint, PlotUi};
+use serde::{Deserialize, Serialize};
+
+use super::{i18n::t, index_at, instrument::Instrument, real_data::RealData};
+
+/// 弱磁吸时指针距离价格多少像素以内才吸附
+const WEAK_DISTANCE: f32 = 12.0;
+
+/// 磁吸模式，十字线和画线锚点吸附到指针处k线的开高低收
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MagnetMode {
+    /// 不吸附，价格对齐到最小变动价位
+    Off,
+    /// 靠近开高低收时才吸附
+    Weak,
+    /// 总是吸附到最近的开高低收
+    Strong,
+}
+
+impl MagnetMode {
+    pub const ALL: [MagnetMode; 3] = [MagnetMode::Off, MagnetMode::Weak, MagnetMode::Strong];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MagnetMode::Off => t("无磁吸"),
+            MagnetMode::Weak => t("弱磁吸"),
+            MagnetMode::Strong => t("强磁吸"),
+        }
+    }
+
+    /// 吸附后的位置，吸附到k线时x为k线的中心
+    ///
+    /// half_distance与KLine中的相同，用于找到指针处的k线
+    pub fn snap(
+        &self,
+        plot_ui: &PlotUi,
+        real_datas: &[RealData],
+        half_distance: f64,
+        instrument: &Instrument,
+        point: PlotPoint,
+    ) -> PlotPoint {
+        let rounded = PlotPoint::new(point.x, instrument.round_price(point.y));
+        if *self == MagnetMode::Off {
+            return rounded;
+        }
+        let Some(real_data) =
+            index_at(real_datas, half_distance, point.x).map(|index| &real_datas[index])
+        else {
+            return rounded;
+        };
+        let (open, close) = real_data.open_close();
+        let spread = &real_data.box_elem.spread;
+        let nearest = [open, spread.upper_whisker, spread.lower_whisker, close]
+            .into_iter()
+            .fold(close, |nearest, price| {
+                if (price - point.y).abs() < (nearest - point.y).abs() {
+                    price
+                } else {
+                    nearest
+                }
+            });
+        let snapped = PlotPoint::new(real_data.box_elem.argument, nearest);
+        let distance =
+            (plot_ui.screen_from_plot(snapped).y - plot_ui.screen_from_plot(point).y).abs();
+        match self {
+            MagnetMode::Weak if distance > WEAK_DISTANCE => rounded,
+            _ => snapped,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use egui::{
+    plot::{Line, PlotPoint, PlotUi, Polygon, Text},
+    Align2, Key, RichText, Stroke,
+};
+
+use super::{
+    colors::CandleColors,
+    i18n::{t, tf},
+    instrument::Instrument,
+    real_data::{Direction, RealData},
+    utils::DateTimeUtils,
+};
+
+/// 测量区域的填充透明度
+const FILL_ALPHA: f32 = 0.15;
+
+/// 测量工具，按住Shift在蜡烛图上拖动，显示两点之间的涨跌、k线数、时间和成交量
+///
+/// 松开后测量结果保留，再次单击或按Esc时清除
+#[derive(Debug, Default)]
+pub struct Measure {
+    /// 拖动的起点和终点，吸附规则与画线锚点相同
+    points: Option<(PlotPoint, PlotPoint)>,
+    /// 是否正在拖动
+    measuring: bool,
+}
+
+/// 两点之间的测量结果
+#[derive(Debug, Clone, PartialEq)]
+struct MeasureStats {
+    change: f64,
+    /// 相对于起点价格的百分比
+    percent: f64,
+    bars: usize,
+    /// 第一根到最后一根k线经过的秒数
+    seconds: i64,
+    volume: f64,
+}
+
+impl MeasureStats {
+    /// 根据起点和终点之间的k线计算，两点的先后顺序不影响k线范围
+    fn new(real_datas: &[RealData], start: PlotPoint, end: PlotPoint) -> Self {
+        let (min, max) = (start.x.min(end.x), start.x.max(end.x));
+        let bars = real_datas
+            .iter()
+            .filter(|real_data| {
+                let x = real_data.box_elem.argument;
+                x >= min.round() && x <= max.round()
+            })
+            .collect::<Vec<&RealData>>();
+        let seconds = match (bars.first(), bars.last()) {
+            (Some(first), Some(last)) => {
+                let start = first.start_datetime.as_ref().unwrap_or(&first.datetime);
+                DateTimeUtils::seconds_between(start, &last.datetime).unwrap_or(0)
+            }
+            _ => 0,
+        };
+        let change = end.y - start.y;
+        Self {
+            change,
+            percent: if start.y != 0.0 {
+                change / start.y * 100.0
+            } else {
+                0.0
+            },
+            bars: bars.len(),
+            seconds,
+            volume: bars.iter().map(|real_data| real_data.candle.volume).sum(),
+        }
+    }
+
+    /// 显示在测量区域旁的文字
+    fn label(&self, instrument: &Instrument) -> String {
+        format!(
+            "{} ({:+.2}%)\n{}\n{} {}",
+            instrument.format_change(self.change),
+            self.percent,
+            tf("{}根k线, {}", &[&self.bars, &format_duration(self.seconds)]),
+            t("成交量"),
+            instrument.format_volume(self.volume),
+        )
+    }
+}
+
+impl Measure {
+    /// 拖动图时是否应该由测量工具处理
+    pub fn pointer_captured(&self) -> bool {
+        self.measuring
+    }
+
+    /// 处理Shift拖动，并绘制测量区域和结果
+    ///
+    /// snap将指针位置吸附到k线或最小变动价位
+    pub fn show(
+        &mut self,
+        plot_ui: &mut PlotUi,
+        real_datas: &[RealData],
+        instrument: &Instrument,
+        colors: &CandleColors,
+        snap: impl Fn(&PlotUi, PlotPoint) -> PlotPoint,
+    ) {
+        self.handle_input(plot_ui, snap);
+        let Some((start, end)) = self.points else {
+            return;
+        };
+        let stats = MeasureStats::new(real_datas, start, end);
+        let color = colors.body(Direction::from_open_close(start.y, end.y));
+        let corners = vec![
+            [start.x, start.y],
+            [end.x, start.y],
+            [end.x, end.y],
+            [start.x, end.y],
+        ];
+        plot_ui.polygon(
+            Polygon::new(corners)
+                .stroke(Stroke::new(0.0, color))
+                .fill_alpha(FILL_ALPHA),
+        );
+        plot_ui.line(Line::new(vec![[start.x, start.y], [end.x, end.y]]).color(color));
+        // 上涨时文字在区域上方，下跌时在下方
+        let (y, anchor) = if end.y >= start.y {
+            (start.y.max(end.y), Align2::CENTER_BOTTOM)
+        } else {
+            (start.y.min(end.y), Align2::CENTER_TOP)
+        };
+        plot_ui.text(
+            Text::new(
+                PlotPoint::new((start.x + end.x) / 2.0, y),
+                RichText::new(stats.label(instrument)).strong(),
+            )
+            .color(color)
+            .anchor(anchor),
+        );
+    }
+
+    fn handle_input(&mut self, plot_ui: &PlotUi, snap: impl Fn(&PlotUi, PlotPoint) -> PlotPoint) {
+        let (pressed, down, shift, escape, pointer) = plot_ui.ctx().input(|input| {
+            (
+                input.pointer.primary_pressed(),
+                input.pointer.primary_down(),
+                input.modifiers.shift,
+                input.key_pressed(Key::Escape),
+                input.pointer.interact_pos(),
+            )
+        });
+        if escape {
+            self.points = None;
+            self.measuring = false;
+        }
+        if self.measuring && !down {
+            self.measuring = false;
+        }
+        let Some(pointer) = pointer else {
+            return;
+        };
+        let point = snap(plot_ui, plot_ui.plot_from_screen(pointer));
+        if pressed && plot_ui.plot_hovered() {
+            if shift {
+                self.points = Some((point, point));
+                self.measuring = true;
+            } else {
+                self.points = None;
+            }
+        } else if self.measuring {
+            if let Some((_, end)) = self.points.as_mut() {
+                *end = point;
+            }
+        }
+    }
+}
+
+/// 将秒数显示为天、小时和分钟，省略前面为0的单位
+fn format_duration(seconds: i64) -> String {
+    let minutes = seconds.abs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        tf("{}天{}小时{}分钟", &[&days, &hours, &minutes])
+    } else if hours > 0 {
+        tf("{}小时{}分钟", &[&hours, &minutes])
+    } else {
+        tf("{}分钟", &[&minutes])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kline::real_data::Candle;
+
+    fn real_datas(count: usize) -> Vec<RealData> {
+        (0..count)
+            .map(|index| {
+                let candle = Candle::for_test(
+                    index as i64 * 5,
+                    100.0,
+                    102.0,
+                    99.0,
+                    101.0,
+                    10.0 * (index + 1) as f64,
+                );
+                RealData::new(&candle, (index + 1) as f64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bars_volume_and_duration() {
+        let stats = MeasureStats::new(
+            &real_datas(5),
+            PlotPoint::new(2.0, 100.0),
+            PlotPoint::new(4.2, 110.0),
+        );
+        assert_eq!(stats.bars, 3);
+        assert_eq!(stats.volume, 90.0);
+        assert_eq!(stats.seconds, 600);
+        assert_eq!(stats.change, 10.0);
+        assert!((stats.percent - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reversed_points() {
+        let real_datas = real_datas(5);
+        let forward = MeasureStats::new(
+            &real_datas,
+            PlotPoint::new(1.0, 100.0),
+            PlotPoint::new(3.0, 100.0),
+        );
+        let backward = MeasureStats::new(
+            &real_datas,
+            PlotPoint::new(3.0, 100.0),
+            PlotPoint::new(1.0, 100.0),
+        );
+        assert_eq!((forward.bars, forward.seconds), (3, 600));
+        assert_eq!(
+            (backward.bars, backward.volume),
+            (forward.bars, forward.volume)
+        );
+        assert_eq!(backward.seconds, forward.seconds);
+    }
+
+    #[test]
+    fn start_of_brick() {
+        let mut real_datas = real_datas(2);
+        real_datas[0].start_datetime = Some("2023-01-01 08:00".to_string());
+        let stats = MeasureStats::new(
+            &real_datas,
+            PlotPoint::new(1.0, 100.0),
+            PlotPoint::new(2.0, 100.0),
+        );
+        assert_eq!(stats.seconds, 65 * 60);
+    }
+
+    #[test]
+    fn duration() {
+        assert_eq!(format_duration(0), "0分钟");
+        assert_eq!(format_duration(59 * 60), "59分钟");
+        assert_eq!(format_duration(3 * 3600 + 5 * 60), "3小时5分钟");
+        assert_eq!(format_duration(86400 + 3600), "1天1小时0分钟");
+        assert_eq!(format_duration(-3600), "1小时0分钟");
+    }
+}
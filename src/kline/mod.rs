@@ -1,67 +1,172 @@
 use std::sync::mpsc::{self, Receiver};
 
 use egui::{
-    plot::{Bar, BarChart, BoxElem, BoxPlot, BoxSpread, HLine, Plot, PlotBounds, VLine},
-    Color32, Context, Id, PointerButton, Pos2, Response, Stroke, Ui, Vec2,
+    plot::{
+        Bar, BarChart, BoxElem, BoxSpread, HLine, Line, LineStyle, Plot, PlotBounds, PlotPoint,
+        PlotUi, Points, Polygon, Text, VLine,
+    },
+    Align2, Button, Color32, ComboBox, Context, Id, Key, Modifiers, PointerButton, Pos2, Response,
+    RichText, TextEdit, Ui, Vec2,
 };
 use poll_promise::Promise;
 use serde::{Deserialize, Serialize};
 use web_sys::console;
 
 use self::{
-    real_data::{Candle, RealData},
+    brick::{BarMode, BrickBuilder, BrickSettings},
+    chart_style::ChartStyle,
+    drawing::Drawings,
+    history::{ChartCommand, History},
+    i18n::{t, tf, Language},
+    indicator::{IndicatorLine, IndicatorSettings, IndicatorStyle},
+    instrument::Instrument,
+    magnet::MagnetMode,
+    measure::Measure,
+    real_data::{Candle, Direction, RealData},
+    selection::RangeSelection,
+    theme::ThemeSettings,
+    tooltip::TooltipSettings,
     utils::{CustomError, DateTimeUtils},
+    volume::{OpenInterestMode, VolumeMode, VolumeSettings},
 };
 
+mod brick;
+mod chart_style;
+mod colors;
+mod drawing;
+mod fibonacci;
+mod formula;
+mod history;
+mod i18n;
+mod indicator;
+mod instrument;
+mod magnet;
+mod measure;
 mod real_data;
+mod selection;
+mod theme;
+mod tooltip;
 mod utils;
+mod volume;
 
 #[derive(Deserialize, Debug)]
 struct CustomResponse {
     code: String,
     message: String,
     data: Vec<Candle>,
+    /// 合约信息，旧的数据源没有这个字段
+    #[serde(default)]
+    instrument: Option<Instrument>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct AxisRange {
     min: f64,
     max: f64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct KLine {
     /// k线图左上角的像素坐标
+    #[serde(skip)]
     offset_pos: Pos2,
     /// k线图的大小
+    #[serde(skip)]
     size: Vec2,
     /// 蜡烛图数据
+    #[serde(skip)]
     candles: Vec<Candle>,
     /// 蜡烛图的个数
+    #[serde(skip)]
     candles_count: f64,
     /// 是否有新数据
+    #[serde(skip)]
     has_new_data: bool,
     /// x轴的范围
+    #[serde(skip)]
     x_range: AxisRange,
     /// 蜡烛图y轴的范围
+    #[serde(skip)]
     y_range: AxisRange,
-    /// 成交量图y轴的最大值
-    y_volume_max: f64,
+    /// 成交量图y轴的范围
+    #[serde(skip)]
+    y_volume_range: AxisRange,
     /// x轴在drag状态下每帧的向量，正负表示方向
+    #[serde(skip)]
     drag_x_move: f32,
     /// 两个蜡烛图的x轴距离
+    #[serde(skip)]
     half_distance: f64,
     /// 当前帧是否双击蜡烛图
+    #[serde(skip)]
     is_candle_double_click: bool,
     /// 当前帧是否双击成交量图
+    #[serde(skip)]
     is_volume_double_click: bool,
     /// 十字线y轴的位置
+    #[serde(skip)]
     v_line_pos: f64,
     /// http是否已执行
+    #[serde(skip)]
     is_http_execute: bool,
     ///
     #[serde(skip)]
     promise: Option<Promise<CustomResponse>>,
+    /// 最近一次获取k线数据失败的原因
+    #[serde(skip)]
+    load_error: Option<String>,
+    /// 指标设置
+    indicator_settings: IndicatorSettings,
+    /// 成交量图设置
+    volume_settings: VolumeSettings,
+    /// 当前帧副图的个数
+    #[serde(skip)]
+    sub_pane_count: usize,
+    /// 是否正在选择锚定VWAP的起点
+    #[serde(skip)]
+    is_anchor_picking: bool,
+    /// 是否使用平均k线(Heikin-Ashi)显示蜡烛图
+    heikin_ashi: bool,
+    /// 蜡烛图的绘制方式
+    chart_style: ChartStyle,
+    /// 砖形图和等幅k线的设置
+    brick_settings: BrickSettings,
+    /// 图表主题
+    theme: ThemeSettings,
+    /// 合约代码
+    code: String,
+    /// k线周期
+    ktype: String,
+    /// 工具栏中正在输入的合约代码
+    #[serde(skip)]
+    code_input: String,
+    /// 工具栏中正在输入的k线周期
+    #[serde(skip)]
+    ktype_input: String,
+    /// 合约信息，随k线数据一起获取
+    #[serde(skip)]
+    instrument: Instrument,
+    /// 悬浮提示设置
+    tooltip_settings: TooltipSettings,
+    /// 界面语言
+    language: Language,
+    /// 砖形图和等幅k线的构建状态
+    #[serde(skip)]
+    brick_builder: BrickBuilder,
+    /// 蜡烛图上的画线
+    drawings: Drawings,
+    /// 十字线和画线锚点的磁吸模式
+    magnet: MagnetMode,
+    /// 按住Shift拖动的测量工具
+    #[serde(skip)]
+    measure: Measure,
+    /// 按住Alt拖动选择的k线区间
+    #[serde(skip)]
+    range_selection: RangeSelection,
+    /// 画线、指标和样式修改的撤销历史
+    #[serde(skip)]
+    history: History<ChartCommand>,
 }
 
 impl Default for KLine {
@@ -80,7 +185,10 @@ impl Default for KLine {
                 min: f64::INFINITY,
                 max: f64::NEG_INFINITY,
             },
-            y_volume_max: f64::NEG_INFINITY,
+            y_volume_range: AxisRange {
+                min: f64::INFINITY,
+                max: f64::NEG_INFINITY,
+            },
             drag_x_move: 0.0,
             half_distance: 0.3,
             is_candle_double_click: false,
@@ -88,6 +196,28 @@ impl Default for KLine {
             v_line_pos: 0.0,
             is_http_execute: false,
             promise: Default::default(),
+            load_error: None,
+            indicator_settings: Default::default(),
+            volume_settings: Default::default(),
+            sub_pane_count: 0,
+            is_anchor_picking: false,
+            heikin_ashi: false,
+            chart_style: ChartStyle::Candle,
+            brick_settings: Default::default(),
+            theme: Default::default(),
+            code: "CZCE.AP.AP401".to_string(),
+            ktype: "m1".to_string(),
+            code_input: String::new(),
+            ktype_input: String::new(),
+            instrument: Default::default(),
+            tooltip_settings: Default::default(),
+            language: Language::ZhCn,
+            brick_builder: Default::default(),
+            drawings: Default::default(),
+            magnet: MagnetMode::Off,
+            measure: Default::default(),
+            range_selection: Default::default(),
+            history: Default::default(),
         }
     }
 }
@@ -99,6 +229,12 @@ impl KLine {
         self.size = clip_rect.size();
     }
 
+    /// 根据权重计算各个图的高度，蜡烛图:成交量图:副图 = 3:2:1.5
+    fn pane_height(&self, weight: f32) -> f32 {
+        (self.size.y - 16.0 * (1.0 + self.sub_pane_count as f32)) * weight
+            / (5.0 + 1.5 * self.sub_pane_count as f32)
+    }
+
     /// 将新的k线数据转换为蜡烛图和成交量图的数据
     fn set_candles(&mut self) -> Vec<RealData> {
         let real_datas = self
@@ -147,21 +283,62 @@ impl KLine {
             min: f64::INFINITY,
             max: f64::NEG_INFINITY,
         };
-        self.y_volume_max = f64::NEG_INFINITY;
+        self.y_volume_range = AxisRange {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        };
     }
 
     /// 设置蜡烛图和成交量图的y轴范围。
-    fn set_y_range(&mut self, real_datas: &Vec<RealData>) {
+    ///
+    /// volume_values是成交量图当前模式下的数值，volume_lines是成交量图上的折线
+    fn set_y_range(
+        &mut self,
+        real_datas: &Vec<RealData>,
+        indicator_lines: &[IndicatorLine],
+        volume_values: &[f64],
+        volume_lines: &[IndicatorLine],
+    ) {
         self.y_range_init();
-        real_datas.iter().for_each(|real_data| {
-            if real_data.box_elem.argument >= self.x_range.min
-                && real_data.box_elem.argument <= self.x_range.max
-            {
-                self.y_range.min = self.y_range.min.min(real_data.box_elem.spread.lower_whisker);
-                self.y_range.max = self.y_range.max.max(real_data.box_elem.spread.upper_whisker);
-                self.y_volume_max = self.y_volume_max.max(real_data.bar.value);
-            }
-        });
+        real_datas
+            .iter()
+            .zip(volume_values.iter())
+            .for_each(|(real_data, volume_value)| {
+                if real_data.box_elem.argument >= self.x_range.min
+                    && real_data.box_elem.argument <= self.x_range.max
+                {
+                    self.y_range.min = self
+                        .y_range
+                        .min
+                        .min(real_data.box_elem.spread.lower_whisker);
+                    self.y_range.max = self
+                        .y_range
+                        .max
+                        .max(real_data.box_elem.spread.upper_whisker);
+                    self.y_volume_range.min = self.y_volume_range.min.min(*volume_value);
+                    self.y_volume_range.max = self.y_volume_range.max.max(*volume_value);
+                }
+            });
+        volume_lines
+            .iter()
+            .flat_map(|line| line.points.iter())
+            .filter(|point| point[0] >= self.x_range.min && point[0] <= self.x_range.max)
+            .for_each(|point| {
+                self.y_volume_range.min = self.y_volume_range.min.min(point[1]);
+                self.y_volume_range.max = self.y_volume_range.max.max(point[1]);
+            });
+        // 柱状图从0开始
+        if self.volume_settings.mode != VolumeMode::Obv && self.y_volume_range.min > 0.0 {
+            self.y_volume_range.min = 0.0;
+        }
+        indicator_lines
+            .iter()
+            .flat_map(|line| line.points.iter())
+            .filter(|point| point[0] >= self.x_range.min && point[0] <= self.x_range.max)
+            .for_each(|point| {
+                self.y_range.min = self.y_range.min.min(point[1]);
+                self.y_range.max = self.y_range.max.max(point[1]);
+            });
     }
 
     /// 增加y轴的范围，在上下边界产生一些空白
@@ -173,8 +350,17 @@ impl KLine {
                 max: self.y_range.max + space,
             };
         }
-        if self.y_volume_max != f64::NEG_INFINITY {
-            self.y_volume_max *= 1.1;
+        if self.y_volume_range.min != f64::INFINITY && self.y_volume_range.max != f64::NEG_INFINITY
+        {
+            let space = (self.y_volume_range.max - self.y_volume_range.min) / 10.0;
+            self.y_volume_range = AxisRange {
+                min: if self.y_volume_range.min < 0.0 {
+                    self.y_volume_range.min - space
+                } else {
+                    self.y_volume_range.min
+                },
+                max: self.y_volume_range.max + space,
+            };
         }
     }
 
@@ -193,23 +379,57 @@ impl KLine {
     // }
 
     /// 创建k线图
-    fn draw_kline(&mut self, ui: &mut Ui, real_datas: &Vec<RealData>, ctx: &Context) -> Response {
+    fn draw_kline(
+        &mut self,
+        ui: &mut Ui,
+        real_datas: &Vec<RealData>,
+        indicator_lines: &[IndicatorLine],
+        ctx: &Context,
+    ) -> Response {
         let datetimes = real_datas
             .iter()
-            .map(|real_data| real_data.datetime.to_owned())
+            .map(|real_data| real_data.time_label())
             .collect::<Vec<String>>();
-        Plot::new("kline")
+        // 最后一根k线之后的日期按k线周期推算，用于显示向前平移的指标，非时间k线无法推算
+        let last_datetime = real_datas
+            .last()
+            .filter(|_| self.brick_settings.mode == BarMode::Time)
+            .map(|real_data| real_data.candle.datetime.to_owned());
+        let interval = DateTimeUtils::interval(
+            &real_datas
+                .iter()
+                .map(|real_data| real_data.candle.datetime.as_str())
+                .collect::<Vec<&str>>(),
+        );
+        let header_height = ui
+            .scope(|ui| self.show_kline_header(ui, real_datas, indicator_lines))
+            .response
+            .rect
+            .height()
+            + ui.spacing().item_spacing.y;
+        let instrument = self.instrument.to_owned();
+        let response = Plot::new("kline")
             .width(self.size.x - 16.0)
-            .height((self.size.y - 16.0) * 0.6)
+            .height(self.pane_height(3.0) - header_height)
             .allow_scroll(false)
             .allow_drag(false)
             .x_axis_formatter(move |x, _r| {
-                if x % 1.0 != 0.0 || datetimes.len() == 0 || x > datetimes.len() as f64 || x < 0.5 {
+                if x % 1.0 != 0.0 || datetimes.len() == 0 || x < 0.5 {
                     String::new()
+                } else if x > datetimes.len() as f64 {
+                    match (&last_datetime, interval) {
+                        (Some(last_datetime), Some(interval)) => DateTimeUtils::add_seconds(
+                            last_datetime,
+                            interval * (x as i64 - datetimes.len() as i64),
+                        )
+                        .unwrap_or_default(),
+                        _ => String::new(),
+                    }
                 } else {
                     datetimes[(x - 1.0) as usize].to_owned()
                 }
             })
+            .y_axis_formatter(move |y, _r| instrument.format_price(y))
             .show_y(false)
             .show_x(false)
             .label_formatter(|_name, _value| String::new())
@@ -229,63 +449,263 @@ impl KLine {
                     [self.x_range.max, self.y_range.max],
                 );
                 plot_ui.set_plot_bounds(plot_bounds);
-                let box_plot = BoxPlot::new(
-                    real_datas
-                        .iter()
-                        .map(|item| item.box_elem.to_owned())
-                        .collect(),
+                // 点数图在plot之后用painter绘制
+                if self.brick_settings.mode != BarMode::PointFigure {
+                    self.chart_style.draw(plot_ui, real_datas);
+                }
+                plot_lines(plot_ui, indicator_lines);
+                let (magnet, half_distance) = (self.magnet, self.half_distance);
+                let instrument = &self.instrument;
+                self.drawings
+                    .show(plot_ui, real_datas, instrument, |plot_ui, point| {
+                        magnet.snap(plot_ui, real_datas, half_distance, instrument, point)
+                    });
+                self.measure.show(
+                    plot_ui,
+                    real_datas,
+                    instrument,
+                    &self.theme.current().colors,
+                    |plot_ui, point| {
+                        magnet.snap(plot_ui, real_datas, half_distance, instrument, point)
+                    },
                 );
-                plot_ui.box_plot(box_plot);
+                self.range_selection
+                    .show(plot_ui, "kline", self.theme.current().crosshair);
+
+                // 最新价标签
+                if let Some(last) = real_datas.last() {
+                    let close = last.open_close().1;
+                    let color = self.theme.current().colors.body(last.direction);
+                    plot_ui.hline(
+                        HLine::new(close)
+                            .color(color)
+                            .style(LineStyle::dashed_loose()),
+                    );
+                    plot_ui.text(
+                        Text::new(
+                            PlotPoint::new(self.x_range.max, close),
+                            RichText::new(self.instrument.format_price(close)).strong(),
+                        )
+                        .color(color)
+                        .anchor(Align2::RIGHT_BOTTOM),
+                    );
+                }
 
                 // 使用K线图整体的y轴十字线
-                plot_ui.vline(VLine::new(self.v_line_pos).color(Color32::BLACK));
+                plot_ui.vline(
+                    VLine::new(self.v_line_pos).stroke(self.theme.current().crosshair_stroke()),
+                );
+
+                // 点击k线选择锚定VWAP的起点
+                if self.is_anchor_picking && plot_ui.plot_clicked() {
+                    if let Some(plot_point) = plot_ui.pointer_coordinate() {
+                        if let Some(index) = self.index_at(real_datas, plot_point.x) {
+                            self.indicator_settings
+                                .add_anchored_vwap(real_datas[index].candle.datetime.to_owned());
+                            self.is_anchor_picking = false;
+                        }
+                    }
+                }
 
                 if plot_ui.plot_hovered() {
                     if let Some(plot_point) = plot_ui.pointer_coordinate() {
-                        plot_ui.hline(HLine::new(plot_point.y).color(Color32::BLACK));
+                        let plot_point = self.magnet.snap(
+                            plot_ui,
+                            real_datas,
+                            self.half_distance,
+                            &self.instrument,
+                            plot_point,
+                        );
+                        plot_ui.hline(
+                            HLine::new(plot_point.y)
+                                .stroke(self.theme.current().crosshair_stroke()),
+                        );
                         // 将位置信息赋值给v_line_pos以便全局使用。
                         self.v_line_pos = plot_point.x;
-                        if let Some(real_data) = real_datas.iter().find(|real_data| {
-                            plot_point.x - self.half_distance < real_data.box_elem.argument
-                                && plot_point.x + self.half_distance > real_data.box_elem.argument
-                        }) {
-                            egui::show_tooltip(ctx, egui::Id::new("tooltip"), |ui| {
-                                let (open, close) = if real_data.box_elem.fill == Color32::RED {
-                                    (
-                                        real_data.box_elem.spread.quartile1,
-                                        real_data.box_elem.spread.quartile3,
-                                    )
-                                } else {
-                                    (
-                                        real_data.box_elem.spread.quartile3,
-                                        real_data.box_elem.spread.quartile1,
-                                    )
-                                };
-                                ui.label(format!("日期: {}", real_data.datetime));
-                                ui.label(format!("开盘: {}", open));
-                                ui.label(format!(
-                                    "最高: {}",
-                                    real_data.box_elem.spread.upper_whisker
-                                ));
-                                ui.label(format!(
-                                    "最低: {}",
-                                    real_data.box_elem.spread.lower_whisker
-                                ));
-                                ui.label(format!("收盘: {}", close));
-                                ui.label(format!("数量: {}", real_data.bar.value));
-                            });
-                        };
+                        if let Some(index) = self.index_at(real_datas, plot_point.x) {
+                            self.tooltip_settings.show(
+                                ctx,
+                                &real_datas[..=index],
+                                indicator_lines,
+                                self.heikin_ashi,
+                                self.theme.current(),
+                                &self.instrument,
+                            );
+                        }
                     }
                 }
             })
-            .response
+            .response;
+        if self.brick_settings.mode == BarMode::PointFigure {
+            self.paint_point_figure(ui, &response, real_datas);
+        }
+        response
+    }
+
+    /// 用X和O绘制点数图，每一格一个符号
+    fn paint_point_figure(&self, ui: &Ui, response: &Response, real_datas: &[RealData]) {
+        let box_size = self.brick_builder.box_size();
+        let rect = response.rect;
+        let (x_span, y_span) = (
+            self.x_range.max - self.x_range.min,
+            self.y_range.max - self.y_range.min,
+        );
+        if box_size <= 0.0 || x_span <= 0.0 || y_span <= 0.0 {
+            return;
+        }
+        // 与set_plot_bounds设置的范围一致
+        let to_screen = |x: f64, y: f64| {
+            Pos2::new(
+                rect.left() + ((x - self.x_range.min) / x_span) as f32 * rect.width(),
+                rect.bottom() - ((y - self.y_range.min) / y_span) as f32 * rect.height(),
+            )
+        };
+        let radius = ((rect.width() / x_span as f32) * self.half_distance as f32)
+            .min(rect.height() / y_span as f32 * box_size as f32 / 2.0)
+            * 0.8;
+        let painter = ui.painter_at(rect);
+        real_datas
+            .iter()
+            .filter(|real_data| {
+                real_data.box_elem.argument >= self.x_range.min - 1.0
+                    && real_data.box_elem.argument <= self.x_range.max + 1.0
+            })
+            .for_each(|real_data| {
+                let candle = &real_data.candle;
+                let is_up = real_data.direction == Direction::Up;
+                // 与蜡烛图影线的颜色和线宽一致
+                let stroke = real_data.box_elem.stroke;
+                let count = ((candle.high - candle.low) / box_size).round() as usize;
+                (0..=count).for_each(|i| {
+                    let center = to_screen(
+                        real_data.box_elem.argument,
+                        candle.low + box_size * i as f64,
+                    );
+                    if is_up {
+                        painter.line_segment(
+                            [
+                                center + Vec2::new(-radius, -radius),
+                                center + Vec2::new(radius, radius),
+                            ],
+                            stroke,
+                        );
+                        painter.line_segment(
+                            [
+                                center + Vec2::new(-radius, radius),
+                                center + Vec2::new(radius, -radius),
+                            ],
+                            stroke,
+                        );
+                    } else {
+                        painter.circle_stroke(center, radius, stroke);
+                    }
+                });
+            });
+    }
+
+    /// 蜡烛图上方的图例，显示十字线处(没有时为最后一根)k线的行情和指标数值
+    fn show_kline_header(
+        &self,
+        ui: &mut Ui,
+        real_datas: &[RealData],
+        indicator_lines: &[IndicatorLine],
+    ) {
+        let index = self
+            .index_at(real_datas, self.v_line_pos)
+            .or_else(|| real_datas.len().checked_sub(1));
+        ui.horizontal_wrapped(|ui| {
+            ui.strong(format!("{} {}", self.code, self.ktype));
+            let Some(index) = index else {
+                return;
+            };
+            let real_data = &real_datas[index];
+            let (open, close) = real_data.open_close();
+            let spread = &real_data.box_elem.spread;
+            let instrument = &self.instrument;
+            ui.label(tf("开: {}", &[&instrument.format_price(open)]));
+            ui.label(tf(
+                "高: {}",
+                &[&instrument.format_price(spread.upper_whisker)],
+            ));
+            ui.label(tf(
+                "低: {}",
+                &[&instrument.format_price(spread.lower_whisker)],
+            ));
+            ui.label(tf("收: {}", &[&instrument.format_price(close)]));
+            if let Some(prev) = index.checked_sub(1).map(|prev| &real_datas[prev]) {
+                let prev_close = prev.open_close().1;
+                let change = close - prev_close;
+                let color = self
+                    .theme
+                    .current()
+                    .colors
+                    .body(Direction::from_open_close(prev_close, close));
+                ui.label(
+                    RichText::new(tf("涨跌: {}", &[&instrument.format_change(change)]))
+                        .color(color),
+                );
+                if prev_close != 0.0 {
+                    ui.label(
+                        RichText::new(tf(
+                            "涨跌幅: {}%",
+                            &[&format!("{:+.2}", change / prev_close * 100.0)],
+                        ))
+                        .color(color),
+                    );
+                    ui.label(tf(
+                        "振幅: {}%",
+                        &[&format!(
+                            "{:.2}",
+                            (spread.upper_whisker - spread.lower_whisker) / prev_close * 100.0
+                        )],
+                    ));
+                }
+            }
+            ui.label(tf(
+                "量: {}",
+                &[&instrument.format_volume(real_data.candle.volume)],
+            ));
+            indicator_lines.iter().for_each(|line| {
+                if let Some(value) = line.value_at(real_data.box_elem.argument) {
+                    ui.label(
+                        RichText::new(format!("{}: {:.2}", line.name, value)).color(line.color),
+                    );
+                }
+            });
+        });
+    }
+
+    /// 找到x坐标处的蜡烛图下标
+    fn index_at(&self, real_datas: &[RealData], x: f64) -> Option<usize> {
+        index_at(real_datas, self.half_distance, x)
     }
 
     /// 创建成交量图
-    fn draw_volume(&mut self, ui: &mut Ui, real_datas: &Vec<RealData>, ctx: &Context) -> Response {
+    fn draw_volume(
+        &mut self,
+        ui: &mut Ui,
+        real_datas: &Vec<RealData>,
+        volume_values: &[f64],
+        volume_lines: &[IndicatorLine],
+        open_interest_line: Option<&IndicatorLine>,
+        ctx: &Context,
+    ) -> Response {
+        // 图例显示十字线处的数值，没有时显示最后一根
+        let index = self
+            .index_at(real_datas, self.v_line_pos)
+            .or_else(|| real_datas.len().checked_sub(1));
+        let header_height = ui
+            .scope(|ui| {
+                self.volume_settings
+                    .show_header(ui, real_datas, volume_values, index);
+            })
+            .response
+            .rect
+            .height()
+            + ui.spacing().item_spacing.y;
         Plot::new("kline_draw")
             .width(self.size.x - 16.0)
-            .height((self.size.y - 16.0) * 0.4)
+            .height(self.pane_height(2.0) - header_height)
             .allow_scroll(false)
             .allow_drag(false)
             .x_axis_formatter(|_x, _r| String::new())
@@ -298,69 +718,368 @@ impl KLine {
                     y: 0.0,
                 });
                 let plot_bounds: PlotBounds = PlotBounds::from_min_max(
-                    [self.x_range.min, 0.0],
-                    [self.x_range.max, self.y_volume_max],
+                    [self.x_range.min, self.y_volume_range.min],
+                    [self.x_range.max, self.y_volume_range.max],
                 );
                 plot_ui.set_plot_bounds(plot_bounds);
-                let chart = BarChart::new(
-                    real_datas
-                        .iter()
-                        .map(|real_data| real_data.bar.to_owned())
-                        .collect(),
-                );
+                let chart = BarChart::new(self.volume_settings.bars(real_datas, volume_values));
                 plot_ui.bar_chart(chart);
+                plot_lines(plot_ui, volume_lines);
+                self.range_selection
+                    .show(plot_ui, "kline_volume", self.theme.current().crosshair);
+                // 叠加的持仓量使用自己的刻度，按可见范围映射到成交量图的y轴范围内
+                if let Some(line) = open_interest_line {
+                    let visible = line.points.iter().filter(|point| {
+                        point[0] >= self.x_range.min && point[0] <= self.x_range.max
+                    });
+                    let (min, max) = visible
+                        .fold((f64::INFINITY, f64::NEG_INFINITY), |acc, point| {
+                            (acc.0.min(point[1]), acc.1.max(point[1]))
+                        });
+                    if min.is_finite() && max.is_finite() {
+                        let scale = if max > min {
+                            (self.y_volume_range.max - self.y_volume_range.min) / (max - min)
+                        } else {
+                            0.0
+                        };
+                        let points = line
+                            .points
+                            .iter()
+                            .map(|point| {
+                                [point[0], self.y_volume_range.min + (point[1] - min) * scale]
+                            })
+                            .collect::<Vec<[f64; 2]>>();
+                        plot_ui.line(
+                            Line::new(points)
+                                .name(&line.name)
+                                .color(line.color)
+                                .width(line.width),
+                        );
+                    }
+                }
 
                 // 使用K线图整体的y轴十字线
-                plot_ui.vline(VLine::new(self.v_line_pos).color(Color32::BLACK));
+                plot_ui.vline(
+                    VLine::new(self.v_line_pos).stroke(self.theme.current().crosshair_stroke()),
+                );
 
                 if plot_ui.plot_hovered() {
                     if let Some(plot_point) = plot_ui.pointer_coordinate() {
-                        plot_ui.hline(HLine::new(plot_point.y).color(Color32::BLACK));
+                        plot_ui.hline(
+                            HLine::new(plot_point.y)
+                                .stroke(self.theme.current().crosshair_stroke()),
+                        );
                         // 将位置信息赋值给v_line_pos以便全局使用。
                         self.v_line_pos = plot_point.x;
-                        if let Some(real_data) = real_datas.iter().find(|real_data| {
-                            plot_point.x - self.half_distance < real_data.bar.argument
-                                && plot_point.x + self.half_distance > real_data.bar.argument
-                        }) {
-                            egui::show_tooltip(ctx, egui::Id::new("tooltip"), |ui| {
-                                let (open, close) = if real_data.box_elem.fill == Color32::RED {
-                                    (
-                                        real_data.box_elem.spread.quartile1,
-                                        real_data.box_elem.spread.quartile3,
-                                    )
-                                } else {
-                                    (
-                                        real_data.box_elem.spread.quartile3,
-                                        real_data.box_elem.spread.quartile1,
-                                    )
-                                };
-                                ui.label(format!("日期: {}", real_data.datetime));
-                                ui.label(format!("开盘: {}", open));
-                                ui.label(format!(
-                                    "最高: {}",
-                                    real_data.box_elem.spread.upper_whisker
-                                ));
-                                ui.label(format!(
-                                    "最低: {}",
-                                    real_data.box_elem.spread.lower_whisker
-                                ));
-                                ui.label(format!("收盘: {}", close));
-                                ui.label(format!("数量: {}", real_data.bar.value));
-                            });
-                        };
+                        if let Some(index) = self.index_at(real_datas, plot_point.x) {
+                            self.tooltip_settings.show(
+                                ctx,
+                                &real_datas[..=index],
+                                volume_lines,
+                                self.heikin_ashi,
+                                self.theme.current(),
+                                &self.instrument,
+                            );
+                        }
+                    }
+                }
+            })
+            .response
+    }
+
+    /// 创建副图，副图有自己的y轴刻度，根据可见范围内的折线计算
+    fn draw_sub_pane(
+        &mut self,
+        ui: &mut Ui,
+        id: &str,
+        real_datas: &[RealData],
+        lines: &[IndicatorLine],
+        ctx: &Context,
+    ) -> Response {
+        let mut y_range = AxisRange {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        };
+        lines
+            .iter()
+            .flat_map(|line| line.points.iter())
+            .filter(|point| point[0] >= self.x_range.min && point[0] <= self.x_range.max)
+            .for_each(|point| {
+                y_range.min = y_range.min.min(point[1]);
+                y_range.max = y_range.max.max(point[1]);
+            });
+        if y_range.min.is_finite() && y_range.max.is_finite() {
+            let space = (y_range.max - y_range.min).max(f64::EPSILON) / 10.0;
+            y_range = AxisRange {
+                min: y_range.min - space,
+                max: y_range.max + space,
+            };
+        } else {
+            y_range = AxisRange { min: 0.0, max: 1.0 };
+        }
+        // 图例显示十字线处的数值，没有时显示最后一根
+        let x = self
+            .index_at(real_datas, self.v_line_pos)
+            .or_else(|| real_datas.len().checked_sub(1))
+            .map(|index| real_datas[index].box_elem.argument);
+        let header_height = ui
+            .horizontal(|ui| {
+                lines.iter().for_each(|line| {
+                    let value = x
+                        .and_then(|x| line.value_at(x))
+                        .map(|value| format!("{:.2}", value))
+                        .unwrap_or_default();
+                    ui.label(RichText::new(format!("{}: {}", line.name, value)).color(line.color));
+                });
+            })
+            .response
+            .rect
+            .height()
+            + ui.spacing().item_spacing.y;
+        Plot::new(id)
+            .width(self.size.x - 16.0)
+            .height(self.pane_height(1.5) - header_height)
+            .allow_scroll(false)
+            .allow_drag(false)
+            .x_axis_formatter(|_x, _r| String::new())
+            .show_y(false)
+            .show_x(false)
+            .label_formatter(|_name, _value| String::new())
+            .show(ui, |plot_ui| {
+                plot_ui.translate_bounds(Vec2 {
+                    x: self.drag_x_move,
+                    y: 0.0,
+                });
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                    [self.x_range.min, y_range.min],
+                    [self.x_range.max, y_range.max],
+                ));
+                plot_lines(plot_ui, lines);
+
+                // 使用K线图整体的y轴十字线
+                plot_ui.vline(
+                    VLine::new(self.v_line_pos).stroke(self.theme.current().crosshair_stroke()),
+                );
+
+                if plot_ui.plot_hovered() {
+                    if let Some(plot_point) = plot_ui.pointer_coordinate() {
+                        plot_ui.hline(
+                            HLine::new(plot_point.y)
+                                .stroke(self.theme.current().crosshair_stroke()),
+                        );
+                        // 将位置信息赋值给v_line_pos以便全局使用。
+                        self.v_line_pos = plot_point.x;
+                        if let Some(index) = self.index_at(real_datas, plot_point.x) {
+                            self.tooltip_settings.show(
+                                ctx,
+                                &real_datas[..=index],
+                                lines,
+                                self.heikin_ashi,
+                                self.theme.current(),
+                                &self.instrument,
+                            );
+                        }
                     }
                 }
             })
             .response
     }
 
+    /// 创建工具栏，返回工具栏的Response
+    fn draw_toolbar(&mut self, ui: &mut Ui) -> Response {
+        ui.horizontal(|ui| {
+            // 输入合约代码或周期后按回车切换，重新请求数据
+            let code_response = ui.add(
+                TextEdit::singleline(&mut self.code_input)
+                    .desired_width(110.0)
+                    .hint_text(t("合约代码")),
+            );
+            let ktype_response = ui.add(
+                TextEdit::singleline(&mut self.ktype_input)
+                    .desired_width(40.0)
+                    .hint_text(t("周期")),
+            );
+            let submitted = (code_response.lost_focus() || ktype_response.lost_focus())
+                && ui.input(|input| input.key_pressed(Key::Enter));
+            let (code, ktype) = (self.code_input.trim(), self.ktype_input.trim());
+            if submitted
+                && !code.is_empty()
+                && !ktype.is_empty()
+                && (code != self.code || ktype != self.ktype)
+            {
+                self.code = code.to_owned();
+                self.ktype = ktype.to_owned();
+                self.is_http_execute = false;
+                // 撤销历史中的画线属于之前的合约和周期
+                self.history.clear();
+            }
+            if !code_response.has_focus() {
+                self.code_input = self.code.to_owned();
+            }
+            if !ktype_response.has_focus() {
+                self.ktype_input = self.ktype.to_owned();
+            }
+            if let Some(err) = &self.load_error {
+                ui.colored_label(Color32::RED, err);
+            }
+            ui.separator();
+            ui.toggle_value(&mut self.indicator_settings.open, t("指标"));
+            ui.toggle_value(&mut self.theme.open, t("主题"));
+            ui.toggle_value(&mut self.tooltip_settings.open, t("悬浮提示"));
+            ComboBox::from_id_source("language")
+                .selected_text(self.language.name())
+                .show_ui(ui, |ui| {
+                    Language::ALL.iter().for_each(|language| {
+                        ui.selectable_value(&mut self.language, *language, language.name());
+                    });
+                });
+            let (chart_style, bricks) = (self.chart_style, self.brick_settings.to_owned());
+            ComboBox::from_id_source("chart_style")
+                .selected_text(self.chart_style.name())
+                .show_ui(ui, |ui| {
+                    ChartStyle::ALL.iter().for_each(|style| {
+                        ui.selectable_value(&mut self.chart_style, *style, style.name());
+                    });
+                });
+            if self.chart_style != chart_style {
+                self.record(ChartCommand::ChartStyle(chart_style, self.chart_style));
+            }
+            self.brick_settings.show_toolbar(ui);
+            if self.brick_settings != bricks {
+                self.record(ChartCommand::Bricks(bricks, self.brick_settings.to_owned()));
+            }
+            if ui
+                .toggle_value(&mut self.heikin_ashi, t("平均K线"))
+                .changed()
+            {
+                self.record(ChartCommand::HeikinAshi(
+                    !self.heikin_ashi,
+                    self.heikin_ashi,
+                ));
+            }
+            ui.toggle_value(&mut self.is_anchor_picking, t("锚定VWAP"))
+                .on_hover_text(t("点击k线作为VWAP的起点"));
+            ui.separator();
+            if ui
+                .add_enabled(self.history.can_undo(), Button::new(t("撤销")))
+                .on_hover_text("Ctrl+Z")
+                .clicked()
+            {
+                self.undo();
+            }
+            if ui
+                .add_enabled(self.history.can_redo(), Button::new(t("重做")))
+                .on_hover_text("Ctrl+Shift+Z")
+                .clicked()
+            {
+                self.redo();
+            }
+            self.drawings.show_toolbar(ui);
+            ComboBox::from_id_source("magnet")
+                .selected_text(self.magnet.name())
+                .show_ui(ui, |ui| {
+                    MagnetMode::ALL.iter().for_each(|mode| {
+                        ui.selectable_value(&mut self.magnet, *mode, mode.name());
+                    });
+                })
+                .response
+                .on_hover_text(t("十字线和画线锚点吸附到k线的开高低收"));
+        })
+        .response
+    }
+
+    /// 记录一个修改，连续的修改中同一个对象的修改合并为一步
+    fn record(&mut self, command: ChartCommand) {
+        self.history.push_or_merge(command, ChartCommand::merge);
+    }
+
+    /// 在一帧的最后记录各部分这一帧的修改
+    ///
+    /// 按住指针(如拖动数值或颜色)或输入框有焦点时是连续的修改，松开或失去焦点后结束
+    fn record_commands(&mut self, ctx: &Context) {
+        let commands = [
+            self.drawings.take_commands(),
+            self.indicator_settings.take_commands(),
+            self.theme.take_commands(),
+        ];
+        commands
+            .into_iter()
+            .flatten()
+            .for_each(|command| self.record(command));
+        let down = ctx.input(|input| input.pointer.any_down());
+        if !down && ctx.memory(|memory| memory.focus().is_none()) {
+            self.history.end_merge();
+        }
+    }
+
+    /// 撤销或重做一个修改
+    fn apply_command(&mut self, command: &ChartCommand, undo: bool) {
+        match command {
+            ChartCommand::Drawing(edit) => self.drawings.apply(edit, undo),
+            ChartCommand::DrawingStyle(before, after) => {
+                self.drawings.style = if undo { before } else { after }.to_owned();
+            }
+            ChartCommand::Indicator(edit) => {
+                edit.apply(&mut self.indicator_settings.indicators, undo);
+            }
+            ChartCommand::ChartStyle(before, after) => {
+                self.chart_style = *if undo { before } else { after };
+            }
+            ChartCommand::HeikinAshi(before, after) => {
+                self.heikin_ashi = *if undo { before } else { after };
+            }
+            ChartCommand::Bricks(before, after) => {
+                self.brick_settings = if undo { before } else { after }.to_owned();
+            }
+            ChartCommand::ThemeMode(before, after) => {
+                self.theme.mode = *if undo { before } else { after };
+            }
+            ChartCommand::Theme(dark, before, after) => {
+                let theme = if undo { before } else { after }.as_ref().to_owned();
+                if *dark {
+                    self.theme.dark = theme;
+                } else {
+                    self.theme.light = theme;
+                }
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(command) = self.history.undo().cloned() {
+            self.apply_command(&command, true);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(command) = self.history.redo().cloned() {
+            self.apply_command(&command, false);
+        }
+    }
+
+    /// Ctrl+Z撤销，Ctrl+Shift+Z重做，输入框有焦点时留给输入框
+    fn handle_undo_keys(&mut self, ctx: &Context) {
+        if ctx.memory(|memory| memory.focus().is_some()) {
+            return;
+        }
+        if ctx.input_mut(|input| input.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z)) {
+            self.redo();
+        } else if ctx.input_mut(|input| input.consume_key(Modifiers::COMMAND, Key::Z)) {
+            self.undo();
+        }
+    }
+
     fn http(&mut self) {
         let (sender, promise) = Promise::new();
+        let query = [
+            ("code", self.code.to_owned()),
+            ("ktype", self.ktype.to_owned()),
+        ];
         wasm_bindgen_futures::spawn_local(async move {
             let client = reqwest::Client::new();
             let res = match client
                 .get("http://localhost:8000/option/KlinePython/optquote/getKLineData")
-                .query(&[("code", "CZCE.AP.AP401"), ("ktype", "m1")])
+                .query(&query)
                 // .fetch_mode_no_cors()
                 .send()
                 .await
@@ -369,14 +1088,16 @@ impl KLine {
                     Ok(custom_response) => custom_response,
                     Err(_) => CustomResponse {
                         code: "1".to_string(),
-                        message: "解析错误".to_string(),
+                        message: t("解析错误").to_string(),
                         data: vec![],
+                        instrument: None,
                     },
                 },
                 Err(_) => CustomResponse {
                     code: "2".to_string(),
-                    message: "请求错误".to_string(),
+                    message: t("请求错误").to_string(),
                     data: vec![],
+                    instrument: None,
                 },
             };
             sender.send(res);
@@ -387,7 +1108,23 @@ impl KLine {
     pub fn show(&mut self, ui: &mut Ui, ctx: &Context) {
         let mut saved_info = SaveInfo::load(ctx, Id::new("save_info")).unwrap_or_default();
         self.set_size(ui);
+        i18n::set_language(self.language);
+        self.theme.update(ui);
+        let toolbar_response = self.draw_toolbar(ui);
+        self.size.y -= toolbar_response.rect.height() + ui.spacing().item_spacing.y;
+        self.indicator_settings.show_window(ctx);
+        self.theme.show_window(ctx);
+        self.tooltip_settings.show_window(ctx);
+        self.drawings.switch(&self.code, &self.ktype);
+        self.handle_undo_keys(ctx);
+        self.drawings.show_window(ctx);
+        self.drawings.show_share_window(ctx);
+        self.drawings.fibonacci.show_window(ctx);
         if !self.is_http_execute {
+            // 切换合约或周期后清空已有的k线
+            saved_info.real_datas.clear();
+            self.candles.clear();
+            self.candles_count = 1.0;
             self.http();
             self.is_http_execute = true;
         }
@@ -395,8 +1132,15 @@ impl KLine {
             if let Some(result) = promise.ready() {
                 if result.code != "0" {
                     console::log_1(&format!("result: {:?}", result).into());
+                    self.load_error = Some(result.message.to_owned());
+                } else if let Some(candle) = result.data.iter().find(|candle| {
+                    DateTimeUtils::format_datetime_string(&candle.datetime).is_none()
+                }) {
+                    self.load_error = Some(tf("日期格式错误: {}", &[&candle.datetime]));
                 } else {
+                    self.load_error = None;
                     self.candles = result.data.to_owned();
+                    self.instrument = result.instrument.to_owned().unwrap_or_default();
                 }
                 self.promise = None;
             } else {
@@ -405,30 +1149,113 @@ impl KLine {
         }
         let mut real_datas = self.set_candles();
         saved_info.real_datas.append(&mut real_datas);
-        self.set_y_range(&saved_info.real_datas);
-        let candle_response = self.draw_kline(ui, &saved_info.real_datas, ctx);
-        let volume_response = self.draw_volume(ui, &saved_info.real_datas, ctx);
-
-        // 拖动其中一个时，两个一起移动
-        if candle_response.dragged_by(PointerButton::Primary)
-            || volume_response.dragged_by(PointerButton::Primary)
-        {
-            self.drag_x_move = if candle_response.dragged() {
-                -candle_response.drag_delta().x
-            } else {
-                -volume_response.drag_delta().x
+        // 平均k线和非时间k线只替换显示的数据，切换时不需要重新请求数据
+        let mut display_datas = match self.brick_settings.mode {
+            BarMode::Time if self.heikin_ashi => RealData::heikin_ashi(&saved_info.real_datas),
+            BarMode::Time => saved_info.real_datas.to_owned(),
+            _ => self
+                .brick_builder
+                .update(&self.brick_settings, &saved_info.real_datas),
+        };
+        display_datas
+            .iter_mut()
+            .for_each(|real_data| real_data.set_colors(self.theme.current()));
+        self.indicator_settings.update_formulas();
+        let indicator_lines = self
+            .indicator_settings
+            .lines(&display_datas, &self.theme.current().colors);
+        let volume_values = self
+            .volume_settings
+            .values(&display_datas, &self.instrument);
+        let volume_lines = self.volume_settings.lines(&display_datas, &volume_values);
+        self.set_y_range(
+            &display_datas,
+            &indicator_lines,
+            &volume_values,
+            &volume_lines,
+        );
+        let open_interest_line = self.volume_settings.open_interest_line(&display_datas);
+        // 副图的id和折线
+        let mut sub_panes = self
+            .indicator_settings
+            .sub_panes(&display_datas, &self.theme.current().colors);
+        if self.volume_settings.open_interest == OpenInterestMode::SubPane {
+            if let Some(line) = open_interest_line.to_owned() {
+                sub_panes.push(("kline_open_interest".to_string(), vec![line]));
             }
-        } else {
-            self.drag_x_move = 0.0;
         }
+        self.sub_pane_count = sub_panes.len();
+        let candle_response = self.draw_kline(ui, &display_datas, &indicator_lines, ctx);
+        let volume_response = self.draw_volume(
+            ui,
+            &display_datas,
+            &volume_values,
+            &volume_lines,
+            open_interest_line
+                .as_ref()
+                .filter(|_| self.volume_settings.open_interest == OpenInterestMode::Overlay),
+            ctx,
+        );
+        let mut responses = vec![candle_response, volume_response];
+        sub_panes.iter().for_each(|(id, lines)| {
+            responses.push(self.draw_sub_pane(ui, id, &display_datas, lines, ctx));
+        });
+
+        // 拖动其中一个时，所有图一起移动，拖动画线、测量或选择区间时不移动
+        let pointer_captured = self.drawings.pointer_captured()
+            || self.measure.pointer_captured()
+            || self.range_selection.pointer_captured();
+        self.drag_x_move = responses
+            .iter()
+            .filter(|_| !pointer_captured)
+            .find(|response| response.dragged_by(PointerButton::Primary))
+            .map(|response| -response.drag_delta().x)
+            .unwrap_or(0.0);
+        self.range_selection
+            .show_window(ctx, &display_datas, &self.instrument);
 
         let saving_info = SaveInfo {
             real_datas: saved_info.real_datas.to_owned(),
         };
         saving_info.store(ctx, Id::new("save_info"));
+        self.record_commands(ctx);
     }
 }
 
+/// 找到x坐标处的蜡烛图下标，half_distance为两个蜡烛图x轴距离的一半
+fn index_at(real_datas: &[RealData], half_distance: f64, x: f64) -> Option<usize> {
+    real_datas.iter().position(|real_data| {
+        x - half_distance < real_data.box_elem.argument
+            && x + half_distance > real_data.box_elem.argument
+    })
+}
+
+/// 在图中绘制指标折线
+fn plot_lines(plot_ui: &mut PlotUi, lines: &[IndicatorLine]) {
+    lines.iter().for_each(|line| match line.style {
+        IndicatorStyle::Line => plot_ui.line(
+            Line::new(line.points.to_owned())
+                .name(&line.name)
+                .color(line.color)
+                .width(line.width),
+        ),
+        IndicatorStyle::Area => plot_ui.polygon(
+            Polygon::new(line.points.to_owned())
+                .name(&line.name)
+                .color(line.color)
+                .fill_alpha(0.2)
+                .width(line.width),
+        ),
+        IndicatorStyle::Dots => plot_ui.points(
+            Points::new(line.points.to_owned())
+                .name(&line.name)
+                .color(line.color)
+                .radius(line.width * 1.5)
+                .filled(true),
+        ),
+    });
+}
+
 /// 一些需要存储以供下一次渲染使用的数据
 #[derive(Deserialize, Serialize, Clone, Debug)]
 struct SaveInfo {
@@ -453,3 +1280,67 @@ impl SaveInfo {
         ctx.data_mut(|d| d.get_persisted(id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{drawing::Drawing, history::ListEdit, *};
+
+    #[test]
+    fn undo_and_redo_commands() {
+        let mut kline = KLine::default();
+        let indicators = kline.indicator_settings.indicators.len();
+        kline.drawings.items.push(Drawing::default());
+        kline.record(ChartCommand::Drawing(Box::new(ListEdit::Add(
+            0,
+            Drawing::default(),
+        ))));
+        let removed = kline.indicator_settings.indicators.remove(0);
+        kline.record(ChartCommand::Indicator(Box::new(ListEdit::Remove(
+            0, removed,
+        ))));
+        kline.chart_style = ChartStyle::Line;
+        kline.record(ChartCommand::ChartStyle(
+            ChartStyle::Candle,
+            ChartStyle::Line,
+        ));
+        kline.undo();
+        assert_eq!(kline.chart_style, ChartStyle::Candle);
+        kline.undo();
+        assert_eq!(kline.indicator_settings.indicators.len(), indicators);
+        kline.undo();
+        assert!(kline.drawings.items.is_empty());
+        assert!(!kline.history.can_undo());
+        (0..3).for_each(|_| kline.redo());
+        assert_eq!(kline.drawings.items, vec![Drawing::default()]);
+        assert_eq!(kline.indicator_settings.indicators.len(), indicators - 1);
+        assert_eq!(kline.chart_style, ChartStyle::Line);
+    }
+
+    #[test]
+    fn continuous_edits_are_one_step() {
+        let mut kline = KLine::default();
+        kline.record(ChartCommand::Bricks(
+            Default::default(),
+            BrickSettings {
+                size: 2.0,
+                ..Default::default()
+            },
+        ));
+        kline.record(ChartCommand::Bricks(
+            BrickSettings {
+                size: 2.0,
+                ..Default::default()
+            },
+            BrickSettings {
+                size: 3.0,
+                ..Default::default()
+            },
+        ));
+        kline.brick_settings.size = 3.0;
+        kline.undo();
+        assert_eq!(kline.brick_settings, BrickSettings::default());
+        assert!(!kline.history.can_undo());
+        kline.redo();
+        assert_eq!(kline.brick_settings.size, 3.0);
+    }
+}
@@ -4,7 +4,7 @@ use egui::{
 };
 use serde::{Deserialize, Serialize};
 
-use super::utils::DateTimeUtils;
+use super::{i18n::format_datetime, theme::ChartTheme, utils::DateTimeUtils};
 
 /// 这个类型是用来解析请求数据的。
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -15,6 +15,56 @@ pub struct Candle {
     pub low: f64,
     pub volume: f64,
     pub datetime: String,
+    /// 持仓量，只有期货数据才有
+    #[serde(default)]
+    pub open_interest: Option<f64>,
+    /// 成交额，没有时使用收盘价*成交量*合约乘数估算
+    #[serde(default)]
+    pub turnover: Option<f64>,
+}
+
+#[cfg(test)]
+impl Candle {
+    /// 测试用的k线，日期为2023-01-01 09:00之后第minute分钟
+    pub fn for_test(minute: i64, open: f64, high: f64, low: f64, close: f64, volume: f64) -> Self {
+        let start = chrono::NaiveDateTime::parse_from_str("2023-01-01T09:00", "%Y-%m-%dT%H:%M")
+            .expect("valid test datetime");
+        Self {
+            open,
+            close,
+            high,
+            low,
+            volume,
+            datetime: (start + chrono::Duration::minutes(minute))
+                .format("%Y-%m-%dT%H:%M")
+                .to_string(),
+            open_interest: None,
+            turnover: None,
+        }
+    }
+}
+
+/// k线的涨跌方向
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    /// 收盘价高于开盘价
+    Up,
+    /// 收盘价低于开盘价
+    Down,
+    /// 收盘价等于开盘价
+    Doji,
+}
+
+impl Direction {
+    pub fn from_open_close(open: f64, close: f64) -> Self {
+        if close > open {
+            Direction::Up
+        } else if close < open {
+            Direction::Down
+        } else {
+            Direction::Doji
+        }
+    }
 }
 
 /// k线图实际用到的数据
@@ -24,46 +74,113 @@ pub struct Candle {
 /// bars是成交量图的数据
 ///
 /// datetime是获取到的时间字符串
+///
+/// candle是原始的k线数据，供指标计算使用
+///
+/// start_datetime是砖形图等非时间k线覆盖的第一根k线的时间
+///
+/// direction是蜡烛图的涨跌方向，平均k线模式下为平均k线的方向
 #[derive(Debug, Clone)]
 pub struct RealData {
     pub box_elem: BoxElem,
     pub bar: Bar,
     pub datetime: String,
+    pub candle: Candle,
+    pub start_datetime: Option<String>,
+    pub direction: Direction,
 }
 
 impl RealData {
     /// 根据传入的candle和count，创建一个RealData类型的数据。
-    /// 
-    /// count会作为x轴坐标。
+    ///
+    /// count会作为x轴坐标，颜色使用默认主题。
     pub fn new(candle: &Candle, count: f64) -> Self {
-        let (quartile1, quartile3, color, bar_color) = if candle.open > candle.close {
-            (candle.close, candle.open, Color32::GREEN, Color32::GREEN)
-        } else if candle.open < candle.close {
-            (candle.open, candle.close, Color32::RED, Color32::RED)
+        let mut real_data = Self {
+            box_elem: Self::box_elem(candle, count),
+            bar: Bar::new(count, candle.volume),
+            // 日期在收到k线数据时已经检查过
+            datetime: DateTimeUtils::format_datetime_string(&candle.datetime)
+                .unwrap_or_else(|| candle.datetime.to_owned()),
+            candle: candle.to_owned(),
+            start_datetime: None,
+            direction: Direction::from_open_close(candle.open, candle.close),
+        };
+        real_data.set_colors(&ChartTheme::default());
+        real_data
+    }
+
+    /// 按涨跌方向设置蜡烛图和成交量图的颜色
+    pub fn set_colors(&mut self, theme: &ChartTheme) {
+        let colors = &theme.colors;
+        self.box_elem.fill = colors.body(self.direction);
+        self.box_elem.stroke = Stroke::new(theme.candle_width, colors.wick(self.direction));
+        let volume_color = if self.candle.volume > 0.0 {
+            colors.volume(self.direction)
         } else {
-            (candle.open, candle.close, Color32::BLACK, Color32::RED)
+            Color32::TRANSPARENT
         };
+        self.bar.fill = volume_color;
+        self.bar.stroke = Stroke::new(1.0, volume_color);
+    }
+
+    /// 按当前语言显示的时间，非时间k线显示覆盖的时间段
+    pub fn time_label(&self) -> String {
+        match &self.start_datetime {
+            Some(start_datetime) if start_datetime != &self.datetime => format!(
+                "{} ~ {}",
+                format_datetime(start_datetime),
+                format_datetime(&self.datetime)
+            ),
+            _ => format_datetime(&self.datetime),
+        }
+    }
+
+    /// 蜡烛图的开盘价和收盘价，平均k线模式下为平均k线的数值
+    pub fn open_close(&self) -> (f64, f64) {
+        let spread = &self.box_elem.spread;
+        match self.direction {
+            Direction::Down => (spread.quartile3, spread.quartile1),
+            _ => (spread.quartile1, spread.quartile3),
+        }
+    }
+
+    /// 根据candle创建蜡烛图，颜色由set_colors设置
+    fn box_elem(candle: &Candle, count: f64) -> BoxElem {
+        let (quartile1, quartile3) = (candle.open.min(candle.close), candle.open.max(candle.close));
         let median = (quartile1 + quartile3) / 2.0;
-        let box_elem = BoxElem::new(
+        BoxElem::new(
             count,
             BoxSpread::new(candle.low, quartile1, median, quartile3, candle.high),
         )
         .whisker_width(0.0)
-        .fill(color)
-        .stroke(Stroke::new(1.0, color));
-        let bar = if candle.volume > 0.0 {
-            Bar::new(count, candle.volume)
-                .fill(bar_color)
-                .stroke(Stroke::new(1.0, bar_color))
-        } else {
-            Bar::new(count, candle.volume)
-                .fill(Color32::TRANSPARENT)
-                .stroke(Stroke::new(0.0, Color32::TRANSPARENT))
-        };
-        Self {
-            box_elem,
-            bar,
-            datetime: DateTimeUtils::format_datetime_string(&candle.datetime),
-        }
+    }
+
+    /// 将蜡烛图替换为平均k线(Heikin-Ashi)，candle仍然保留原始数据
+    pub fn heikin_ashi(real_datas: &[RealData]) -> Vec<RealData> {
+        let mut prev: Option<(f64, f64)> = None;
+        real_datas
+            .iter()
+            .map(|real_data| {
+                let candle = &real_data.candle;
+                let close = (candle.open + candle.high + candle.low + candle.close) / 4.0;
+                let open = match prev {
+                    Some((prev_open, prev_close)) => (prev_open + prev_close) / 2.0,
+                    None => (candle.open + candle.close) / 2.0,
+                };
+                prev = Some((open, close));
+                let heikin_ashi = Candle {
+                    open,
+                    close,
+                    high: candle.high.max(open).max(close),
+                    low: candle.low.min(open).min(close),
+                    ..candle.to_owned()
+                };
+                RealData {
+                    box_elem: Self::box_elem(&heikin_ashi, real_data.box_elem.argument),
+                    direction: Direction::from_open_close(open, close),
+                    ..real_data.to_owned()
+                }
+            })
+            .collect()
     }
 }
@@ -0,0 +1,300 @@
+use egui::{
+    plot::{PlotUi, Polygon},
+    Color32, Context, Grid, Id, Stroke, Window,
+};
+
+use super::{
+    i18n::{format_datetime, format_number, t},
+    instrument::Instrument,
+    real_data::{Direction, RealData},
+};
+
+/// 选择区域的填充透明度
+const FILL_ALPHA: f32 = 0.12;
+
+/// 区间选择，按住Alt在蜡烛图或成交量图上拖动选择一段k线，在窗口中显示统计
+#[derive(Debug, Default)]
+pub struct RangeSelection {
+    /// 拖动的起点和终点的x坐标
+    range: Option<(f64, f64)>,
+    /// 正在拖动选择的图的id
+    pane: Option<&'static str>,
+    /// 统计窗口是否打开
+    open: bool,
+}
+
+/// 所选k线的统计
+#[derive(Debug, Clone, PartialEq)]
+struct RangeStats {
+    start: String,
+    end: String,
+    bars: usize,
+    open: f64,
+    close: f64,
+    high: f64,
+    low: f64,
+    /// 从第一根k线开盘到最后一根k线收盘的涨跌幅(%)
+    change_percent: f64,
+    /// 收盘价从最高点回落的最大幅度(%)
+    max_drawdown: f64,
+    /// 相邻收盘价涨跌幅的标准差(%)
+    volatility: f64,
+    average_volume: f64,
+    up: usize,
+    down: usize,
+}
+
+impl RangeStats {
+    /// 没有k线时返回None
+    fn new(real_datas: &[RealData]) -> Option<Self> {
+        let (first, last) = (real_datas.first()?, real_datas.last()?);
+        let candles = real_datas.iter().map(|real_data| &real_data.candle);
+        let (open, close) = (first.candle.open, last.candle.close);
+        let (high, low) = candles
+            .clone()
+            .fold((f64::NEG_INFINITY, f64::INFINITY), |(high, low), candle| {
+                (high.max(candle.high), low.min(candle.low))
+            });
+        let (_, max_drawdown) =
+            candles
+                .clone()
+                .fold((open, 0.0_f64), |(peak, drawdown), candle| {
+                    let peak = peak.max(candle.close);
+                    let current = if peak > 0.0 {
+                        (peak - candle.close) / peak * 100.0
+                    } else {
+                        0.0
+                    };
+                    (peak, drawdown.max(current))
+                });
+        let returns = real_datas
+            .windows(2)
+            .filter(|window| window[0].candle.close != 0.0)
+            .map(|window| (window[1].candle.close / window[0].candle.close - 1.0) * 100.0)
+            .collect::<Vec<f64>>();
+        let volatility = if returns.len() > 1 {
+            let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+            let variance = returns
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f64>()
+                / (returns.len() - 1) as f64;
+            variance.sqrt()
+        } else {
+            0.0
+        };
+        let count = |direction: Direction| {
+            candles
+                .clone()
+                .filter(|candle| Direction::from_open_close(candle.open, candle.close) == direction)
+                .count()
+        };
+        Some(Self {
+            start: first
+                .start_datetime
+                .to_owned()
+                .unwrap_or_else(|| first.datetime.to_owned()),
+            end: last.datetime.to_owned(),
+            bars: real_datas.len(),
+            open,
+            close,
+            high,
+            low,
+            change_percent: if open != 0.0 {
+                (close / open - 1.0) * 100.0
+            } else {
+                0.0
+            },
+            max_drawdown,
+            volatility,
+            average_volume: candles.clone().map(|candle| candle.volume).sum::<f64>()
+                / real_datas.len() as f64,
+            up: count(Direction::Up),
+            down: count(Direction::Down),
+        })
+    }
+
+    /// 显示和复制用的(名称, 值)
+    fn rows(&self, instrument: &Instrument) -> Vec<(&'static str, String)> {
+        vec![
+            (t("开始时间"), format_datetime(&self.start)),
+            (t("结束时间"), format_datetime(&self.end)),
+            (t("k线数"), self.bars.to_string()),
+            (t("开盘"), instrument.format_price(self.open)),
+            (t("收盘"), instrument.format_price(self.close)),
+            (t("最高"), instrument.format_price(self.high)),
+            (t("最低"), instrument.format_price(self.low)),
+            (t("涨跌幅"), format!("{:+.2}%", self.change_percent)),
+            (t("最大回撤"), format!("{:.2}%", self.max_drawdown)),
+            (t("波动率"), format!("{:.2}%", self.volatility)),
+            (t("平均成交量"), format_number(self.average_volume, 0)),
+            (t("上涨k线"), self.up.to_string()),
+            (t("下跌k线"), self.down.to_string()),
+        ]
+    }
+}
+
+impl RangeSelection {
+    /// 拖动图时是否应该由区间选择处理
+    pub fn pointer_captured(&self) -> bool {
+        self.pane.is_some()
+    }
+
+    /// 处理pane图上的Alt拖动，并绘制选择的区域
+    pub fn show(&mut self, plot_ui: &mut PlotUi, pane: &'static str, color: Color32) {
+        self.handle_input(plot_ui, pane);
+        let Some((start, end)) = self.range else {
+            return;
+        };
+        // 区域覆盖整根k线
+        let (left, right) = (start.min(end).round() - 0.5, start.max(end).round() + 0.5);
+        let bounds = plot_ui.plot_bounds();
+        let (bottom, top) = (bounds.min()[1], bounds.max()[1]);
+        plot_ui.polygon(
+            Polygon::new(vec![
+                [left, bottom],
+                [right, bottom],
+                [right, top],
+                [left, top],
+            ])
+            .stroke(Stroke::new(0.0, color))
+            .fill_alpha(FILL_ALPHA),
+        );
+    }
+
+    fn handle_input(&mut self, plot_ui: &PlotUi, pane: &'static str) {
+        let (pressed, down, alt, pointer) = plot_ui.ctx().input(|input| {
+            (
+                input.pointer.primary_pressed(),
+                input.pointer.primary_down(),
+                input.modifiers.alt,
+                input.pointer.interact_pos(),
+            )
+        });
+        let Some(pointer) = pointer else {
+            return;
+        };
+        let x = plot_ui.plot_from_screen(pointer).x;
+        if pressed && alt && plot_ui.plot_hovered() {
+            self.range = Some((x, x));
+            self.pane = Some(pane);
+            self.open = true;
+        } else if self.pane == Some(pane) {
+            if let Some((_, end)) = self.range.as_mut() {
+                *end = x;
+            }
+            if !down {
+                self.pane = None;
+            }
+        }
+    }
+
+    /// 显示所选k线的统计窗口，关闭窗口时取消选择
+    pub fn show_window(&mut self, ctx: &Context, real_datas: &[RealData], instrument: &Instrument) {
+        let Some((start, end)) = self.range else {
+            return;
+        };
+        let (min, max) = (start.min(end).round(), start.max(end).round());
+        let selected = real_datas
+            .iter()
+            .filter(|real_data| {
+                real_data.box_elem.argument >= min && real_data.box_elem.argument <= max
+            })
+            .cloned()
+            .collect::<Vec<RealData>>();
+        let rows = RangeStats::new(&selected).map(|stats| stats.rows(instrument));
+        let mut open = self.open;
+        Window::new(t("区间统计"))
+            .id(Id::new("range_statistics"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some(rows) = &rows else {
+                    ui.label(t("所选区间没有k线"));
+                    return;
+                };
+                Grid::new("range_statistics_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        rows.iter().for_each(|(name, value)| {
+                            ui.label(*name);
+                            ui.label(value);
+                            ui.end_row();
+                        });
+                    });
+                ui.horizontal(|ui| {
+                    if ui.button(t("复制")).clicked() {
+                        let text = rows
+                            .iter()
+                            .map(|(name, value)| format!("{}\t{}", name, value))
+                            .collect::<Vec<String>>()
+                            .join("\n");
+                        ui.output_mut(|output| output.copied_text = text);
+                    }
+                    if ui.button(t("取消选择")).clicked() {
+                        self.open = false;
+                    }
+                });
+            });
+        self.open = open && self.open;
+        if !self.open {
+            self.range = None;
+            self.pane = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kline::real_data::Candle;
+
+    fn real_datas(prices: &[(f64, f64)]) -> Vec<RealData> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(index, (open, close))| {
+                let candle = Candle::for_test(
+                    index as i64,
+                    *open,
+                    open.max(*close) + 1.0,
+                    open.min(*close) - 1.0,
+                    *close,
+                    10.0 * (index + 1) as f64,
+                );
+                RealData::new(&candle, (index + 1) as f64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn range_stats() {
+        let stats =
+            RangeStats::new(&real_datas(&[(100.0, 110.0), (110.0, 99.0), (99.0, 121.0)])).unwrap();
+        assert_eq!(stats.bars, 3);
+        assert_eq!((stats.open, stats.close), (100.0, 121.0));
+        assert_eq!((stats.high, stats.low), (122.0, 98.0));
+        assert!((stats.change_percent - 21.0).abs() < 1e-9);
+        assert!((stats.max_drawdown - 10.0).abs() < 1e-9);
+        assert!((stats.average_volume - 20.0).abs() < 1e-9);
+        assert_eq!((stats.up, stats.down), (2, 1));
+        assert_eq!(stats.start, "2023-01-01 09:00");
+        assert_eq!(stats.end, "2023-01-01 09:02");
+        // 两次涨跌幅为-10%和22.22%
+        assert!((stats.volatility - 22.7846).abs() < 1e-3);
+    }
+
+    #[test]
+    fn range_stats_single_bar() {
+        let stats = RangeStats::new(&real_datas(&[(100.0, 95.0)])).unwrap();
+        assert_eq!(stats.max_drawdown, 5.0);
+        assert_eq!(stats.volatility, 0.0);
+        assert_eq!((stats.up, stats.down), (0, 1));
+    }
+
+    #[test]
+    fn range_stats_empty() {
+        assert_eq!(RangeStats::new(&[]), None);
+    }
+}
@@ -0,0 +1,270 @@
+use egui::{Color32, Context, DragValue, Grid, Id, Stroke, TextEdit, Ui, Visuals, Window};
+use serde::{Deserialize, Serialize};
+
+use super::{colors::CandleColors, history::ChartCommand, i18n::t};
+
+/// 使用哪一套主题
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ThemeMode {
+    /// 跟随系统的深浅色设置
+    System,
+    Light,
+    Dark,
+}
+
+impl ThemeMode {
+    pub const ALL: [ThemeMode; 3] = [ThemeMode::System, ThemeMode::Light, ThemeMode::Dark];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThemeMode::System => t("跟随系统"),
+            ThemeMode::Light => t("浅色"),
+            ThemeMode::Dark => t("深色"),
+        }
+    }
+}
+
+/// 图表主题，可以导入导出为JSON
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ChartTheme {
+    /// 图的背景色
+    pub background: Color32,
+    /// 网格线的颜色，坐标轴文字按对比度由它和背景色混合得到
+    pub grid: Color32,
+    /// 十字线的颜色
+    pub crosshair: Color32,
+    /// 文字的颜色
+    pub text: Color32,
+    /// 蜡烛图和成交量图的配色
+    pub colors: CandleColors,
+    /// 十字线的宽度
+    pub crosshair_width: f32,
+    /// 蜡烛图影线和边框的宽度
+    pub candle_width: f32,
+}
+
+impl Default for ChartTheme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+impl ChartTheme {
+    pub fn light() -> Self {
+        let visuals = Visuals::light();
+        Self {
+            background: visuals.extreme_bg_color,
+            grid: visuals.widgets.open.fg_stroke.color,
+            crosshair: Color32::BLACK,
+            text: visuals.text_color(),
+            colors: CandleColors::with_doji(Color32::BLACK),
+            crosshair_width: 1.0,
+            candle_width: 1.0,
+        }
+    }
+
+    pub fn dark() -> Self {
+        let visuals = Visuals::dark();
+        Self {
+            background: visuals.extreme_bg_color,
+            grid: visuals.widgets.open.fg_stroke.color,
+            crosshair: Color32::LIGHT_GRAY,
+            text: visuals.text_color(),
+            colors: CandleColors::with_doji(Color32::LIGHT_GRAY),
+            crosshair_width: 1.0,
+            candle_width: 1.0,
+        }
+    }
+
+    /// 十字线的样式
+    pub fn crosshair_stroke(&self) -> Stroke {
+        Stroke::new(self.crosshair_width, self.crosshair)
+    }
+
+    /// 将背景、网格和文字颜色应用到ui上，之后在这个ui中创建的图都会使用
+    pub fn apply(&self, visuals: &mut Visuals) {
+        visuals.extreme_bg_color = self.background;
+        visuals.widgets.open.fg_stroke.color = self.grid;
+        visuals.override_text_color = Some(self.text);
+    }
+
+    fn show_ui(&mut self, ui: &mut Ui) {
+        Grid::new("chart_theme").show(ui, |ui| {
+            [
+                (t("背景"), &mut self.background),
+                (t("网格"), &mut self.grid),
+                (t("十字线"), &mut self.crosshair),
+                (t("文字"), &mut self.text),
+            ]
+            .into_iter()
+            .for_each(|(name, color)| {
+                ui.label(name);
+                ui.color_edit_button_srgba(color);
+                ui.end_row();
+            });
+            ui.label(t("十字线宽度"));
+            ui.add(
+                DragValue::new(&mut self.crosshair_width)
+                    .clamp_range(0.5..=5.0)
+                    .speed(0.1),
+            );
+            ui.end_row();
+            ui.label(t("蜡烛图线宽"));
+            ui.add(
+                DragValue::new(&mut self.candle_width)
+                    .clamp_range(0.5..=5.0)
+                    .speed(0.1),
+            );
+            ui.end_row();
+        });
+        ui.separator();
+        self.colors.show_ui(ui);
+    }
+}
+
+/// 主题设置，浅色和深色主题分别保存，会随App一起存储
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ThemeSettings {
+    pub mode: ThemeMode,
+    pub light: ChartTheme,
+    pub dark: ChartTheme,
+    /// 系统当前是否为深色模式
+    #[serde(skip)]
+    dark_mode: bool,
+    /// 主题窗口是否打开
+    #[serde(skip)]
+    pub open: bool,
+    /// 导入导出用的JSON文本
+    #[serde(skip)]
+    json: String,
+    /// 导入失败的原因
+    #[serde(skip)]
+    json_error: Option<String>,
+    /// 还没有记录到撤销历史的修改
+    #[serde(skip)]
+    commands: Vec<ChartCommand>,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            mode: ThemeMode::System,
+            light: ChartTheme::light(),
+            dark: ChartTheme::dark(),
+            dark_mode: false,
+            open: false,
+            json: String::new(),
+            json_error: None,
+            commands: vec![],
+        }
+    }
+}
+
+impl ThemeSettings {
+    /// 每帧开始时记录系统的深浅色设置，并把当前主题应用到ui上
+    pub fn update(&mut self, ui: &mut Ui) {
+        self.dark_mode = ui.visuals().dark_mode;
+        self.current().apply(ui.visuals_mut());
+    }
+
+    /// 取出这一帧的修改，由图表记录到撤销历史
+    pub fn take_commands(&mut self) -> Vec<ChartCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn is_dark(&self) -> bool {
+        match self.mode {
+            ThemeMode::System => self.dark_mode,
+            ThemeMode::Light => false,
+            ThemeMode::Dark => true,
+        }
+    }
+
+    /// 当前使用的主题
+    pub fn current(&self) -> &ChartTheme {
+        if self.is_dark() {
+            &self.dark
+        } else {
+            &self.light
+        }
+    }
+
+    fn current_mut(&mut self) -> &mut ChartTheme {
+        if self.is_dark() {
+            &mut self.dark
+        } else {
+            &mut self.light
+        }
+    }
+
+    /// 显示主题设置窗口，修改的是当前使用的主题
+    pub fn show_window(&mut self, ctx: &Context) {
+        let mut open = self.open;
+        Window::new(t("主题"))
+            .id(Id::new("theme_settings"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let mode = self.mode;
+                ui.horizontal(|ui| {
+                    ThemeMode::ALL.iter().for_each(|mode| {
+                        ui.selectable_value(&mut self.mode, *mode, mode.name());
+                    });
+                });
+                if self.mode != mode {
+                    self.commands.push(ChartCommand::ThemeMode(mode, self.mode));
+                }
+                let (dark, before) = (self.is_dark(), self.current().to_owned());
+                ui.separator();
+                self.current_mut().show_ui(ui);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button(t("导出")).clicked() {
+                        match serde_json::to_string_pretty(self.current()) {
+                            Ok(json) => {
+                                ui.output_mut(|output| output.copied_text = json.to_owned());
+                                self.json = json;
+                                self.json_error = None;
+                            }
+                            Err(err) => self.json_error = Some(err.to_string()),
+                        }
+                    }
+                    if ui.button(t("导入")).clicked() {
+                        match serde_json::from_str::<ChartTheme>(&self.json) {
+                            Ok(theme) => {
+                                *self.current_mut() = theme;
+                                self.json_error = None;
+                            }
+                            Err(err) => self.json_error = Some(err.to_string()),
+                        }
+                    }
+                    if ui.button(t("重置")).clicked() {
+                        *self.current_mut() = if self.is_dark() {
+                            ChartTheme::dark()
+                        } else {
+                            ChartTheme::light()
+                        };
+                    }
+                });
+                ui.add(
+                    TextEdit::multiline(&mut self.json)
+                        .code_editor()
+                        .desired_rows(6)
+                        .hint_text(t("粘贴主题JSON后点击导入")),
+                );
+                if let Some(err) = &self.json_error {
+                    ui.colored_label(Color32::RED, err);
+                }
+                if *self.current() != before {
+                    self.commands.push(ChartCommand::Theme(
+                        dark,
+                        Box::new(before),
+                        Box::new(self.current().to_owned()),
+                    ));
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,269 @@
+use egui::{Context, DragValue, Id, RichText, Window};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    i18n::{format_large, format_number, t, tf},
+    indicator::IndicatorLine,
+    instrument::Instrument,
+    real_data::{Direction, RealData},
+    theme::ChartTheme,
+};
+
+/// 悬浮提示中可以显示的内容
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TooltipField {
+    Datetime,
+    Open,
+    High,
+    Low,
+    Close,
+    /// 相对前一根收盘价的涨跌
+    Change,
+    /// 相对前一根收盘价的涨跌幅
+    ChangePercent,
+    /// 振幅，(最高-最低)/前一根收盘价
+    Amplitude,
+    Volume,
+    Turnover,
+    OpenInterest,
+    /// 当前图中的指标数值
+    Indicators,
+}
+
+impl TooltipField {
+    pub const ALL: [TooltipField; 12] = [
+        TooltipField::Datetime,
+        TooltipField::Open,
+        TooltipField::High,
+        TooltipField::Low,
+        TooltipField::Close,
+        TooltipField::Change,
+        TooltipField::ChangePercent,
+        TooltipField::Amplitude,
+        TooltipField::Volume,
+        TooltipField::Turnover,
+        TooltipField::OpenInterest,
+        TooltipField::Indicators,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TooltipField::Datetime => t("日期"),
+            TooltipField::Open => t("开盘"),
+            TooltipField::High => t("最高"),
+            TooltipField::Low => t("最低"),
+            TooltipField::Close => t("收盘"),
+            TooltipField::Change => t("涨跌"),
+            TooltipField::ChangePercent => t("涨跌幅"),
+            TooltipField::Amplitude => t("振幅"),
+            TooltipField::Volume => t("数量"),
+            TooltipField::Turnover => t("成交额"),
+            TooltipField::OpenInterest => t("持仓量"),
+            TooltipField::Indicators => t("指标"),
+        }
+    }
+}
+
+/// 悬浮提示的设置，会随App一起存储，所有图共用
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TooltipSettings {
+    /// 是否显示悬浮提示，关闭时只使用图例
+    pub enabled: bool,
+    /// 显示的内容，按TooltipField::ALL的顺序显示
+    pub fields: Vec<TooltipField>,
+    /// 指标数值保留的小数位数，价格按合约的小数位数显示
+    pub precision: usize,
+    /// 设置窗口是否打开
+    #[serde(skip)]
+    pub open: bool,
+}
+
+impl Default for TooltipSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            fields: TooltipField::ALL.to_vec(),
+            precision: 2,
+            open: false,
+        }
+    }
+}
+
+impl TooltipSettings {
+    /// 显示悬浮处k线的悬浮提示，real_datas是到悬浮处为止的k线，lines是当前图中的折线
+    ///
+    /// heikin_ashi为true时蜡烛图的数值带"平均"前缀，并始终显示原始k线。
+    /// 涨跌、涨跌幅和振幅都按原始k线计算
+    pub fn show(
+        &self,
+        ctx: &Context,
+        real_datas: &[RealData],
+        lines: &[IndicatorLine],
+        heikin_ashi: bool,
+        theme: &ChartTheme,
+        instrument: &Instrument,
+    ) {
+        let Some(real_data) = real_datas.last().filter(|_| self.enabled) else {
+            return;
+        };
+        let precision = self.precision;
+        let (open, close) = real_data.open_close();
+        let spread = &real_data.box_elem.spread;
+        let candle = &real_data.candle;
+        let prev_close = real_datas
+            .len()
+            .checked_sub(2)
+            .map(|prev| real_datas[prev].candle.close);
+        // 价格按相对前一根收盘价的涨跌着色
+        let color_of = |price: f64| match prev_close {
+            Some(prev_close) => theme
+                .colors
+                .body(Direction::from_open_close(prev_close, price)),
+            None => theme.text,
+        };
+        let price_label = |name: &str, price: f64| {
+            let prefix = if heikin_ashi { t("平均") } else { "" };
+            RichText::new(format!(
+                "{}{}: {}",
+                prefix,
+                name,
+                instrument.format_price(price)
+            ))
+            .color(color_of(price))
+        };
+        egui::show_tooltip(ctx, Id::new("tooltip"), |ui| {
+            if heikin_ashi {
+                ui.label(tf(
+                    "原始: {} / {} / {} / {}",
+                    &[
+                        &instrument.format_price(candle.open),
+                        &instrument.format_price(candle.high),
+                        &instrument.format_price(candle.low),
+                        &instrument.format_price(candle.close),
+                    ],
+                ));
+            }
+            TooltipField::ALL
+                .iter()
+                .filter(|field| self.fields.contains(field))
+                .for_each(|field| match field {
+                    TooltipField::Datetime => {
+                        ui.label(format!("{}: {}", field.name(), real_data.time_label()));
+                    }
+                    TooltipField::Open => {
+                        ui.label(price_label(field.name(), open));
+                    }
+                    TooltipField::High => {
+                        ui.label(price_label(field.name(), spread.upper_whisker));
+                    }
+                    TooltipField::Low => {
+                        ui.label(price_label(field.name(), spread.lower_whisker));
+                    }
+                    TooltipField::Close => {
+                        ui.label(price_label(field.name(), close));
+                    }
+                    TooltipField::Change => {
+                        if let Some(prev_close) = prev_close {
+                            ui.label(
+                                RichText::new(format!(
+                                    "{}: {}",
+                                    field.name(),
+                                    instrument.format_change(candle.close - prev_close)
+                                ))
+                                .color(color_of(candle.close)),
+                            );
+                        }
+                    }
+                    TooltipField::ChangePercent => {
+                        if let Some(prev_close) = prev_close.filter(|prev| *prev != 0.0) {
+                            ui.label(
+                                RichText::new(format!(
+                                    "{}: {:+.2}%",
+                                    field.name(),
+                                    (candle.close - prev_close) / prev_close * 100.0
+                                ))
+                                .color(color_of(candle.close)),
+                            );
+                        }
+                    }
+                    TooltipField::Amplitude => {
+                        if let Some(prev_close) = prev_close.filter(|prev| *prev != 0.0) {
+                            ui.label(format!(
+                                "{}: {:.2}%",
+                                field.name(),
+                                (candle.high - candle.low) / prev_close * 100.0
+                            ));
+                        }
+                    }
+                    TooltipField::Volume => {
+                        ui.label(format!(
+                            "{}: {}",
+                            field.name(),
+                            instrument.format_volume(candle.volume)
+                        ));
+                    }
+                    TooltipField::Turnover => {
+                        ui.label(format!(
+                            "{}: {}",
+                            field.name(),
+                            format_large(instrument.turnover(candle))
+                        ));
+                    }
+                    TooltipField::OpenInterest => {
+                        if let Some(open_interest) = candle.open_interest {
+                            ui.label(format!(
+                                "{}: {}",
+                                field.name(),
+                                format_number(open_interest, 0)
+                            ));
+                        }
+                    }
+                    TooltipField::Indicators => {
+                        lines.iter().for_each(|line| {
+                            if let Some(value) = line.value_at(real_data.box_elem.argument) {
+                                ui.label(
+                                    RichText::new(format!(
+                                        "{}: {:.*}",
+                                        line.name, precision, value
+                                    ))
+                                    .color(line.color),
+                                );
+                            }
+                        });
+                    }
+                });
+        });
+    }
+
+    /// 显示悬浮提示设置窗口
+    pub fn show_window(&mut self, ctx: &Context) {
+        let mut open = self.open;
+        Window::new(t("悬浮提示"))
+            .id(Id::new("tooltip_settings"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut self.enabled, t("显示悬浮提示"));
+                ui.horizontal(|ui| {
+                    ui.label(t("小数位数"));
+                    ui.add(DragValue::new(&mut self.precision).clamp_range(0..=8));
+                });
+                ui.separator();
+                TooltipField::ALL.iter().for_each(|field| {
+                    let mut checked = self.fields.contains(field);
+                    if ui.checkbox(&mut checked, field.name()).changed() {
+                        if checked {
+                            self.fields.push(*field);
+                        } else {
+                            self.fields.retain(|item| item != field);
+                        }
+                    }
+                });
+                if ui.button(t("重置")).clicked() {
+                    self.fields = TooltipField::ALL.to_vec();
+                }
+            });
+        self.open = open;
+    }
+}
@@ -1,8 +1,12 @@
 use std::{error, fmt};
 
-use chrono::{TimeZone, Utc, DateTime};
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc, Weekday,
+};
 use web_sys::console;
 
+use super::i18n::tf;
+
 /// 一个简单的日期操作工具
 pub struct DateTimeUtils;
 
@@ -17,22 +21,71 @@ impl DateTimeUtils {
         }
     }
 
-    /// 将日期字符串转换为时间戳
-    pub fn datetime_to_timestamp(datetime: &str) -> f64 {
-        let date_time = DateTime::parse_from_rfc3339(&(datetime.to_string() + ":00+00:00")).expect("日期字符解析失败");
+    /// 将日期字符串转换为时间戳，日期格式不正确时返回None
+    pub fn datetime_to_timestamp(datetime: &str) -> Option<f64> {
+        let date_time = DateTime::parse_from_rfc3339(&(datetime.to_string() + ":00+00:00")).ok()?;
         // console::log_1(&format!("rr.timestamp() as f64: {}", date_time.timestamp() as f64).into());
         // let date_time = Utc
         //     .datetime_from_str(datetime, "%Y-%m-%d %H:%M")
         //     .expect("日期字符解析失败");
         let timestamp = date_time.timestamp() as f64;
         console::log_1(&format!("timestamp: {}", timestamp).into());
-        timestamp
+        Some(timestamp)
     }
     
-    /// 转换日期的格式，将%Y-%m-%dT%H:%M
-    pub fn format_datetime_string(datetime: &str) -> String {
-        let date_time = DateTime::parse_from_rfc3339(&(datetime.to_string() + ":00+00:00")).expect("日期字符解析失败");
-        format!("{}", date_time.format("%Y-%m-%d %H:%M"))
+    /// 转换日期的格式，将%Y-%m-%dT%H:%M转换为%Y-%m-%d %H:%M，日期格式不正确时返回None
+    pub fn format_datetime_string(datetime: &str) -> Option<String> {
+        let date_time = DateTime::parse_from_rfc3339(&(datetime.to_string() + ":00+00:00")).ok()?;
+        Some(format!("{}", date_time.format("%Y-%m-%d %H:%M")))
+    }
+
+    /// 根据%Y-%m-%dT%H:%M格式的日期序列推算k线周期(秒)，取最近几根k线中最小的时间间隔
+    pub fn interval(datetimes: &[&str]) -> Option<i64> {
+        let timestamps = datetimes
+            .iter()
+            .rev()
+            .take(10)
+            .filter_map(|datetime| NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M").ok())
+            .map(|date_time| date_time.timestamp())
+            .collect::<Vec<i64>>();
+        timestamps
+            .windows(2)
+            .map(|window| (window[0] - window[1]).abs())
+            .filter(|interval| *interval > 0)
+            .min()
+    }
+
+    /// 在%Y-%m-%dT%H:%M格式的日期上加上seconds秒，返回%Y-%m-%d %H:%M格式的日期
+    pub fn add_seconds(datetime: &str, seconds: i64) -> Option<String> {
+        let date_time = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M").ok()?;
+        let date_time = date_time + Duration::seconds(seconds);
+        Some(format!("{}", date_time.format("%Y-%m-%d %H:%M")))
+    }
+
+    /// 计算两个日期之间相差的秒数，日期可以是%Y-%m-%dT%H:%M或%Y-%m-%d %H:%M格式
+    pub fn seconds_between(start: &str, end: &str) -> Option<i64> {
+        let parse = |datetime: &str| {
+            NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M")
+                .or_else(|_| NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M"))
+                .ok()
+        };
+        Some((parse(end)? - parse(start)?).num_seconds())
+    }
+
+    /// 获取%Y-%m-%dT%H:%M格式的日期所属的交易日
+    ///
+    /// 国内期货夜盘(18点以后)属于下一个交易日，周五夜盘以及周六凌晨的数据属于下周一，节假日不做处理。
+    pub fn trading_day(datetime: &str) -> Option<NaiveDate> {
+        let date_time = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M").ok()?;
+        let mut date = if date_time.hour() >= 18 {
+            date_time.date() + Duration::days(1)
+        } else {
+            date_time.date()
+        };
+        while date.weekday() == Weekday::Sat || date.weekday() == Weekday::Sun {
+            date += Duration::days(1);
+        }
+        Some(date)
     }
 }
 
@@ -40,12 +93,15 @@ impl DateTimeUtils {
 #[derive(Debug)]
 pub enum CustomError {
     Http(reqwest::Error),
+    /// 指标公式的语法错误
+    Formula(String),
 }
 
 impl fmt::Display for CustomError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             CustomError::Http(err) => write!(f, "Http error: {}", err),
+            CustomError::Formula(message) => write!(f, "{}", tf("公式错误: {}", &[message])),
         }
     }
 }
@@ -54,12 +110,14 @@ impl error::Error for CustomError {
     fn description(&self) -> &str {
         match self {
             CustomError::Http(err) => err.description(),
+            CustomError::Formula(message) => message,
         }
     }
 
     fn cause(&self) -> Option<&dyn error::Error> {
         match self {
             CustomError::Http(err) => Some(err),
+            CustomError::Formula(_) => None,
         }
     }
 }
@@ -0,0 +1,252 @@
+use egui::{plot::Bar, Color32, ComboBox, RichText, Ui};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    i18n::{format_large, t},
+    indicator::{sma, IndicatorLine, IndicatorStyle},
+    instrument::Instrument,
+    real_data::RealData,
+};
+
+/// 成交量均线的颜色，按周期顺序循环使用
+const MA_COLORS: [Color32; 4] = [
+    Color32::from_rgb(255, 165, 0),
+    Color32::from_rgb(30, 144, 255),
+    Color32::from_rgb(186, 85, 211),
+    Color32::from_rgb(46, 139, 87),
+];
+
+/// 成交量图的显示模式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum VolumeMode {
+    /// 成交量柱
+    Volume,
+    /// 能量潮
+    Obv,
+    /// 成交额柱(价格*成交量)
+    Turnover,
+}
+
+impl VolumeMode {
+    pub const ALL: [VolumeMode; 3] = [VolumeMode::Volume, VolumeMode::Obv, VolumeMode::Turnover];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VolumeMode::Volume => t("成交量"),
+            VolumeMode::Obv => "OBV",
+            VolumeMode::Turnover => t("成交额"),
+        }
+    }
+}
+
+/// 持仓量的显示方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum OpenInterestMode {
+    /// 不显示
+    Hidden,
+    /// 以折线叠加在成交量图上
+    Overlay,
+    /// 单独的副图
+    SubPane,
+}
+
+impl OpenInterestMode {
+    pub const ALL: [OpenInterestMode; 3] = [
+        OpenInterestMode::Hidden,
+        OpenInterestMode::Overlay,
+        OpenInterestMode::SubPane,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OpenInterestMode::Hidden => t("不显示持仓"),
+            OpenInterestMode::Overlay => t("叠加持仓"),
+            OpenInterestMode::SubPane => t("持仓副图"),
+        }
+    }
+}
+
+/// 成交量图的设置，会随App一起存储
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct VolumeSettings {
+    pub mode: VolumeMode,
+    /// 是否显示均线
+    pub show_ma: bool,
+    /// 均线周期
+    pub ma_periods: Vec<usize>,
+    /// 持仓量的显示方式
+    pub open_interest: OpenInterestMode,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self {
+            mode: VolumeMode::Volume,
+            show_ma: true,
+            ma_periods: vec![5, 10],
+            open_interest: OpenInterestMode::Hidden,
+        }
+    }
+}
+
+impl VolumeSettings {
+    /// 根据当前模式计算每根k线对应的值
+    pub fn values(&self, real_datas: &[RealData], instrument: &Instrument) -> Vec<f64> {
+        match self.mode {
+            VolumeMode::Volume => real_datas
+                .iter()
+                .map(|real_data| real_data.candle.volume)
+                .collect(),
+            VolumeMode::Turnover => real_datas
+                .iter()
+                .map(|real_data| instrument.turnover(&real_data.candle))
+                .collect(),
+            VolumeMode::Obv => {
+                let mut obv = 0.0;
+                let mut prev_close: Option<f64> = None;
+                real_datas
+                    .iter()
+                    .map(|real_data| {
+                        let candle = &real_data.candle;
+                        if let Some(prev_close) = prev_close {
+                            if candle.close > prev_close {
+                                obv += candle.volume;
+                            } else if candle.close < prev_close {
+                                obv -= candle.volume;
+                            }
+                        }
+                        prev_close = Some(candle.close);
+                        obv
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// 柱状图数据，OBV模式下没有柱子
+    pub fn bars(&self, real_datas: &[RealData], values: &[f64]) -> Vec<Bar> {
+        match self.mode {
+            VolumeMode::Obv => vec![],
+            _ => real_datas
+                .iter()
+                .zip(values.iter())
+                .map(|(real_data, value)| {
+                    let mut bar = real_data.bar.to_owned();
+                    bar.value = *value;
+                    bar
+                })
+                .collect(),
+        }
+    }
+
+    /// 折线数据，包括OBV线和均线
+    pub fn lines(&self, real_datas: &[RealData], values: &[f64]) -> Vec<IndicatorLine> {
+        let to_line = |name: String, values: Vec<Option<f64>>, color: Color32| IndicatorLine {
+            name,
+            points: real_datas
+                .iter()
+                .zip(values.iter())
+                .filter_map(|(real_data, value)| value.map(|v| [real_data.bar.argument, v]))
+                .collect(),
+            color,
+            width: 1.0,
+            style: IndicatorStyle::Line,
+        };
+        let mut lines = vec![];
+        if self.mode == VolumeMode::Obv {
+            lines.push(to_line(
+                "OBV".to_string(),
+                values.iter().map(|value| Some(*value)).collect(),
+                Color32::GRAY,
+            ));
+        }
+        if self.show_ma {
+            self.ma_periods
+                .iter()
+                .enumerate()
+                .for_each(|(index, period)| {
+                    lines.push(to_line(
+                        format!("MA{}", period),
+                        sma(values, *period),
+                        MA_COLORS[index % MA_COLORS.len()],
+                    ));
+                });
+        }
+        lines
+    }
+
+    /// 持仓量折线，数据中没有持仓量时返回None
+    pub fn open_interest_line(&self, real_datas: &[RealData]) -> Option<IndicatorLine> {
+        let points = real_datas
+            .iter()
+            .filter_map(|real_data| {
+                real_data
+                    .candle
+                    .open_interest
+                    .map(|open_interest| [real_data.bar.argument, open_interest])
+            })
+            .collect::<Vec<[f64; 2]>>();
+        if points.is_empty() {
+            None
+        } else {
+            Some(IndicatorLine {
+                name: t("持仓量").to_string(),
+                points,
+                color: Color32::from_rgb(218, 165, 32),
+                width: 1.0,
+                style: IndicatorStyle::Line,
+            })
+        }
+    }
+
+    /// 成交量图上方的图例，可以切换模式并显示index处的数值
+    pub fn show_header(
+        &mut self,
+        ui: &mut Ui,
+        real_datas: &[RealData],
+        values: &[f64],
+        index: Option<usize>,
+    ) {
+        ui.horizontal(|ui| {
+            VolumeMode::ALL.iter().for_each(|mode| {
+                ui.selectable_value(&mut self.mode, *mode, mode.name());
+            });
+            ui.toggle_value(&mut self.show_ma, "MA");
+            ComboBox::from_id_source("open_interest_mode")
+                .selected_text(self.open_interest.name())
+                .show_ui(ui, |ui| {
+                    OpenInterestMode::ALL.iter().for_each(|mode| {
+                        ui.selectable_value(&mut self.open_interest, *mode, mode.name());
+                    });
+                });
+            ui.separator();
+            if let Some(index) = index {
+                if let Some(value) = values.get(index) {
+                    ui.label(format!("{}: {}", self.mode.name(), format_large(*value)));
+                }
+                if self.show_ma {
+                    self.ma_periods
+                        .iter()
+                        .enumerate()
+                        .for_each(|(color_index, period)| {
+                            if let Some(Some(ma)) = sma(values, *period).get(index) {
+                                ui.label(
+                                    RichText::new(format!("MA{}: {}", period, format_large(*ma)))
+                                        .color(MA_COLORS[color_index % MA_COLORS.len()]),
+                                );
+                            }
+                        });
+                }
+                if self.open_interest == OpenInterestMode::Overlay {
+                    if let Some(open_interest) = real_datas
+                        .get(index)
+                        .and_then(|real_data| real_data.candle.open_interest)
+                    {
+                        ui.label(format!("{}: {}", t("持仓量"), format_large(open_interest)));
+                    }
+                }
+            }
+        });
+    }
+}